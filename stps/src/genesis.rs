@@ -0,0 +1,193 @@
+use sender_lib::workload::AssetTransfer;
+use serde_json::{json, Value};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{collections::HashSet, error::Error};
+
+/// Relay chain `HostConfiguration` override applied to every spawned network.
+pub fn relay_defaults() -> Value {
+	json!({ "configuration": { "config": { "executor_params": [ { "MaxMemoryPages": 8192 }, { "PvfExecTimeout": [ "Backing", 2500 ] } ] } } })
+}
+
+/// Capacity, in messages, of the HRMP channels opened in genesis.
+const HRMP_CAPACITY: u32 = 1000;
+/// Maximal size of a message of the HRMP channels opened in genesis.
+const HRMP_MESSAGE_SIZE: u32 = 4096;
+
+/// Opens HRMP channels in both directions between every pair of the given parachains in the relay
+/// chain `genesis` override. The `HostConfiguration` HRMP limits are raised accordingly, as the
+/// defaults only allow a handful of messages in flight.
+pub fn open_hrmp_channels(genesis: &mut Value, para_ids: &[u32]) {
+	let channels = para_ids
+		.iter()
+		.flat_map(|sender| para_ids.iter().map(move |recipient| (*sender, *recipient)))
+		.filter(|(sender, recipient)| sender != recipient)
+		.map(|(sender, recipient)| json!([sender, recipient, HRMP_CAPACITY, HRMP_MESSAGE_SIZE]));
+	append(genesis, &["hrmp", "preopenHrmpChannels"], channels);
+
+	let peers = para_ids.len().saturating_sub(1).max(4);
+	merge(
+		genesis,
+		json!({ "configuration": { "config": {
+			"hrmp_channel_max_capacity": HRMP_CAPACITY,
+			"hrmp_channel_max_message_size": HRMP_MESSAGE_SIZE,
+			"hrmp_channel_max_total_size": HRMP_CAPACITY * HRMP_MESSAGE_SIZE,
+			"hrmp_max_message_num_per_candidate": HRMP_CAPACITY,
+			"hrmp_max_parachain_outbound_channels": peers,
+			"hrmp_max_parachain_inbound_channels": peers,
+		} } }),
+	);
+}
+
+/// Reads a user-supplied genesis override from the JSON file at `path`.
+pub fn load(path: &str) -> Result<Value, Box<dyn Error>> {
+	let content = std::fs::read_to_string(path)
+		.map_err(|e| format!("Cannot read genesis override {path}: {e}"))?;
+	Ok(serde_json::from_str(&content)
+		.map_err(|e| format!("Cannot parse genesis override {path}: {e}"))?)
+}
+
+/// Endows every `(address, amount)` pair of `accounts` in the `Balances` genesis config of the
+/// `genesis` override, keeping the balances it may already hold.
+pub fn fund(genesis: &mut Value, accounts: &[(String, u64)]) {
+	append(genesis, &["balances", "balances"], accounts.iter().map(|account| json!(account)));
+}
+
+/// Creates the sufficient asset `id` owned by `owner` in the `Assets` genesis config of the
+/// `genesis` override, and endows every one of the `holders` with it.
+pub fn fund_asset(genesis: &mut Value, id: u32, owner: &str, holders: &[String]) {
+	append(genesis, &["assets", "assets"], [json!([id, owner, true, 1])]);
+	append(
+		genesis,
+		&["assets", "accounts"],
+		holders.iter().map(|holder| json!([id, holder, AssetTransfer::MINTED])),
+	);
+}
+
+/// Appends `items` to the array at `path` of `genesis`, creating it if needed.
+fn append(genesis: &mut Value, path: &[&str], items: impl IntoIterator<Item = Value>) {
+	let pointer = path.iter().map(|key| format!("/{key}")).collect::<String>();
+	match genesis.pointer_mut(&pointer).and_then(Value::as_array_mut) {
+		Some(array) => array.extend(items),
+		None => {
+			let array = Value::Array(items.into_iter().collect());
+			let overlay = path.iter().rev().fold(array, |value, key| json!({ (*key): value }));
+			merge(genesis, overlay);
+		},
+	}
+}
+
+/// Deep-merges `overlay` into `base`. Objects are merged key by key, while any other value of
+/// `overlay` replaces the corresponding one of `base`.
+pub fn merge(base: &mut Value, overlay: Value) {
+	match (base, overlay) {
+		(Value::Object(base), Value::Object(overlay)) =>
+			for (key, value) in overlay {
+				merge(base.entry(key).or_insert(Value::Null), value);
+			},
+		(base, overlay) => *base = overlay,
+	}
+}
+
+/// Checks whether the plain chain spec at `path` already endows all the `accounts` in its
+/// `Balances` genesis config. Accounts are given as raw account ids, and may be listed in the chain
+/// spec either as SS58 or as 0x-prefixed hex addresses. Raw chain specs cannot be inspected nor
+/// overridden, so they are reported as funded, leaving it to the user to make sure the accounts are
+/// there.
+pub fn chainspec_funds(
+	path: &str,
+	accounts: impl IntoIterator<Item = Vec<u8>>,
+) -> Result<bool, Box<dyn Error>> {
+	let spec: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+	let genesis = &spec["genesis"];
+
+	if !genesis["raw"].is_null() {
+		log::warn!("Chain spec {path} is raw, assuming the benchmark accounts are already funded");
+		return Ok(true);
+	}
+
+	let runtime = [
+		&genesis["runtimeGenesis"]["patch"],
+		&genesis["runtimeGenesis"]["config"],
+		&genesis["runtime"],
+	];
+	let endowed = runtime
+		.into_iter()
+		.filter_map(|r| r["balances"]["balances"].as_array())
+		.flatten()
+		.filter_map(|entry| entry[0].as_str())
+		.filter_map(|address| match address.strip_prefix("0x") {
+			Some(hex) => hex::decode(hex).ok(),
+			None => AccountId32::from_ss58check_with_version(address)
+				.ok()
+				.map(|(account, _)| AsRef::<[u8]>::as_ref(&account).to_vec()),
+		})
+		.collect::<HashSet<_>>();
+
+	Ok(accounts.into_iter().all(|account| endowed.contains(&account)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn merge_merges_objects_and_replaces_other_values() {
+		let mut base = json!({ "a": { "b": 1, "c": [1, 2] }, "d": "base" });
+		merge(&mut base, json!({ "a": { "c": [3], "e": true }, "d": null, "f": 2 }));
+		assert_eq!(base, json!({ "a": { "b": 1, "c": [3], "e": true }, "d": null, "f": 2 }));
+	}
+
+	#[test]
+	fn append_extends_existing_arrays() {
+		let mut genesis = json!({ "balances": { "balances": [["alice", 1]] } });
+		fund(&mut genesis, &[("bob".to_owned(), 2)]);
+		assert_eq!(genesis, json!({ "balances": { "balances": [["alice", 1], ["bob", 2]] } }));
+	}
+
+	#[test]
+	fn append_creates_missing_arrays() {
+		let mut genesis = json!({ "balances": { "devAccounts": null }, "other": 1 });
+		fund(&mut genesis, &[("bob".to_owned(), 2)]);
+		assert_eq!(
+			genesis,
+			json!({ "balances": { "devAccounts": null, "balances": [["bob", 2]] }, "other": 1 })
+		);
+	}
+
+	#[test]
+	fn open_hrmp_channels_pairs_every_para_both_ways() {
+		let mut genesis = json!({ "hrmp": { "preopenHrmpChannels": [[1, 2, 3, 4]] } });
+		open_hrmp_channels(&mut genesis, &[100, 101, 102]);
+
+		let channels = genesis["hrmp"]["preopenHrmpChannels"].as_array().unwrap();
+		assert_eq!(channels.len(), 1 + 6);
+		assert_eq!(channels[0], json!([1, 2, 3, 4]));
+		for (sender, recipient) in [(100, 101), (100, 102), (101, 100), (101, 102), (102, 100)] {
+			let channel = json!([sender, recipient, HRMP_CAPACITY, HRMP_MESSAGE_SIZE]);
+			assert!(channels.contains(&channel), "{channel} is missing");
+		}
+		assert!(!channels.contains(&json!([100, 100, HRMP_CAPACITY, HRMP_MESSAGE_SIZE])));
+
+		let config = &genesis["configuration"]["config"];
+		assert_eq!(config["hrmp_channel_max_capacity"], json!(HRMP_CAPACITY));
+		assert_eq!(config["hrmp_max_parachain_outbound_channels"], json!(4));
+	}
+
+	#[test]
+	fn chainspec_funds_reads_ss58_and_hex_addresses() {
+		const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+		let alice = AsRef::<[u8]>::as_ref(&AccountId32::from_ss58check(ALICE).unwrap()).to_vec();
+		let bob = vec![0x42; 32];
+		let spec = json!({ "genesis": { "runtimeGenesis": { "patch": { "balances": { "balances": [
+			[ALICE, 1],
+			[format!("0x{}", hex::encode(&bob)), 1],
+		] } } } } });
+		let path = std::env::temp_dir().join(format!("stps-chainspec-{}.json", std::process::id()));
+		std::fs::write(&path, spec.to_string()).unwrap();
+		let path = path.to_str().unwrap();
+
+		assert!(chainspec_funds(path, [alice.clone(), bob.clone()]).unwrap());
+		assert!(!chainspec_funds(path, [alice, vec![0x43; 32]]).unwrap());
+		std::fs::remove_file(path).unwrap();
+	}
+}
@@ -1,25 +1,48 @@
-use clap::{Parser, ValueEnum};
-use futures::{stream::FuturesUnordered, StreamExt};
+use clap::Parser;
+use futures::{stream::FuturesUnordered, StreamExt, TryStreamExt};
 use jsonrpsee_client_transport::ws::WsTransportClientBuilder;
 use jsonrpsee_core::client::Client;
-use parity_scale_codec::{Compact, Decode};
-use sender_lib::PairSigner;
-use serde_json::json;
-use sp_core::{crypto::Ss58Codec, sr25519::Pair as SrPair, Pair};
-use std::{cmp::max, collections::HashMap, error::Error, sync::Arc, time::Duration};
+use sender_lib::{
+	keypair::{KeyType, Keypair},
+	workload::{RemarkCall, XcmDirection, XcmTransferType},
+	Mortality, TxParams, TxStage, TxTimes,
+};
+use std::{
+	error::Error,
+	future::Future,
+	path::PathBuf,
+	process::ExitCode,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 use subxt::{
-	backend::legacy::LegacyBackend, config::DefaultExtrinsicParamsBuilder,
-	dynamic::Value as TxValue, OnlineClient, PolkadotConfig,
+	backend::legacy::LegacyBackend, dynamic::Value as TxValue, ext::scale_value::At,
+	tx::SubmittableTransaction, OnlineClient, PolkadotConfig,
+};
+use tokio::{
+	sync::{mpsc, oneshot, watch},
+	task::{JoinError, JoinHandle},
+};
+use zombienet_sdk::{
+	LocalFileSystem, Network, NetworkConfigBuilder, NetworkConfigExt, NetworkNode,
+	RegistrationStrategy,
 };
-use tokio::sync::mpsc::{self, UnboundedSender};
-use zombienet_sdk::{NetworkConfigBuilder, NetworkConfigExt, NetworkNode, RegistrationStrategy};
+mod genesis;
 mod metrics;
+mod scenario;
+mod subscriber;
 use metrics::*;
+use scenario::*;
+use subscriber::*;
 
 /// Default derivation path for pre-funded accounts
 const SENDER_SEED: &str = "//Sender";
 const RECEIVER_SEED: &str = "//Receiver";
+/// Derivation path prefix of the fresh receivers of the `fresh-transfer` mode.
+const FRESH_SEED: &str = "//Fresh";
 const FUNDS: u64 = 10_000_000_000_000_000;
+/// Exit code of a benchmark reaching its deadline, telling a stalled run from a crashed one.
+const TIMEOUT_EXIT_CODE: u8 = 2;
 
 struct HostnameGen {
 	prefix: String,
@@ -37,54 +60,123 @@ impl HostnameGen {
 	}
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-#[value(rename_all = "kebab-case")]
-pub enum BenchMode {
-	/// Standard balance transfers
-	Stps,
-
-	/// NFT transfers
-	NftTransfer,
-}
-
+/// Command line arguments. Every value given here overrides the one from the scenario file, if
+/// any; values given neither here nor in the scenario take the defaults noted below.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-	/// The ss58 prefix to use (https://github.com/paritytech/ss58-registry/blob/main/ss58-registry.json)
-	#[arg(long, short, default_value_t = 42_u16)]
-	ss58_prefix: u16,
+	/// Path to a TOML scenario file describing the topology, the workload and the measurement
+	/// settings of the run.
+	#[arg(long)]
+	scenario: Option<PathBuf>,
+
+	/// The ss58 prefix to use (https://github.com/paritytech/ss58-registry/blob/main/ss58-registry.json).
+	/// Defaults to 42.
+	#[arg(long, short)]
+	ss58_prefix: Option<u16>,
 
 	/// Number of threads to spawn for account deriving, transaction signing and transaction sending.
 	/// Defaults to 4. If set to 0, defaults to the available number of CPU cores.
-	#[arg(long, short, default_value_t = 4_usize)]
-	threads: usize,
+	#[arg(long, short)]
+	threads: Option<usize>,
 
 	/// Number of transactions PER THREAD. There will be derived threads*count accounts and as much
-	/// transactions will be signed and submitted.
-	#[arg(long, short, default_value_t = 100_usize)]
-	count: usize,
+	/// transactions will be signed and submitted. Defaults to 100.
+	#[arg(long, short)]
+	count: Option<usize>,
+
+	/// Benchmark mode. Defaults to `stps`.
+	#[arg(long, short, value_enum)]
+	mode: Option<BenchMode>,
 
-	/// Benchmark mode
-	#[arg(long, short, value_enum, default_value_t = BenchMode::Stps)]
-	mode: BenchMode,
+	/// Weighted modes to run at once, as a comma-separated list of `mode=weight`, e.g.
+	/// `transfer=70,nft-transfer=20,remark=10`. The senders are assigned to the modes by weight, and
+	/// TPS is reported for every mode as well as overall. Implies `--mode mix`.
+	#[arg(long)]
+	mix: Option<String>,
 
 	/// Number of sender and receiver accounts to create. By defauilt, threads*count senders and as many
-	/// receivers are created. With this option, that number may be overridden, but it shouldn't be less
-	/// than threads*count.
+	/// receivers are created, divided by `--txs-per-account`. With this option, that number may be
+	/// overridden, but it shouldn't be less than the default.
 	#[arg(long)]
 	accounts: Option<usize>,
 
-	/// Path to relay chain node binary.
-	#[arg(long, default_value = "polkadot")]
-	relay_bin: String,
+	/// Number of transactions signed by every sender, with consecutive nonces, so that large numbers
+	/// of transactions can be sent without deriving and funding as many accounts. Defaults to 1.
+	#[arg(long)]
+	txs_per_account: Option<usize>,
+
+	/// Key type of the benchmark accounts, to compare the cost of verifying the signatures of every
+	/// `MultiSignature` variant. Defaults to `sr25519`, or Ethereum keys in the `eth-transfer` mode.
+	#[arg(long, value_enum)]
+	key_type: Option<KeyType>,
+
+	/// Tips paid by the senders of the measured transactions: `TIP` for the same tip for everyone,
+	/// `MIN-MAX` for tips spread across a range, or `FRACTION:TIP` for a fraction of the senders
+	/// paying a tip and the others nothing. With tips, the inclusion latency of the transactions
+	/// is reported by tip. Defaults to 0.
+	#[arg(long)]
+	tip: Option<TipDistribution>,
 
-	/// Args for relay chain binary.
+	/// Sign the measured transactions with a mortal era of this many blocks, rounded up to a power
+	/// of two, anchored at the best block when they are signed. The transactions which expire
+	/// before their inclusion are reported, apart from those dropped for another reason. By
+	/// default, transactions are immortal.
+	#[arg(long)]
+	mortality: Option<u64>,
+
+	/// Asset id of the `asset-transfer` mode. Defaults to 1. With a non-sufficient asset, every sender
+	/// creates its own asset, with consecutive ids from an offset of this one unique to the run.
+	#[arg(long)]
+	asset_id: Option<u32>,
+
+	/// Use a sufficient asset in the `asset-transfer` mode. It is created in genesis and endowed to
+	/// the senders, as only root can create one; when attaching to a network, it must already exist
+	/// and be held by the senders. `--asset-sufficient=false` overrides the scenario.
+	#[arg(long, num_args = 0..=1, default_missing_value = "true")]
+	asset_sufficient: Option<bool>,
+
+	/// Transfer type of the `xcm-transfer` mode. Defaults to `reserve`.
+	#[arg(long, value_enum)]
+	xcm_transfer: Option<XcmTransferType>,
+
+	/// Direction of the transfers of the `xcm-transfer` mode, between the relay chain and the first
+	/// parachain, or from the first parachain to the second one. Defaults to `relay-to-para`.
+	#[arg(long, value_enum)]
+	xcm_direction: Option<XcmDirection>,
+
+	/// Path to the Wasm code of the ERC20 contract of the `contract-transfer` mode, e.g. the ink!
+	/// ERC20 example, or any contract with the same `new` constructor and `transfer` message.
+	#[arg(long)]
+	contract_code: Option<String>,
+
+	/// Payload sizes of the `remark` mode, in bytes, as a comma-separated list. Defaults to 32. With
+	/// several sizes, e.g. `32,1024,65536`, the benchmark is run once per size, reporting the bytes
+	/// per second and per block, and how close the blocks get to their length limit.
+	#[arg(long, value_delimiter = ',')]
+	remark_size: Vec<usize>,
+
+	/// Call of the `remark` mode. Defaults to `remark-with-event`.
+	#[arg(long, value_enum)]
+	remark_call: Option<RemarkCall>,
+
+	/// WebSocket URL of an already running node to benchmark instead of spawning a network. May be
+	/// given multiple times to spread the transactions across several nodes; blocks are scraped from
+	/// the first one. The derived sender and receiver accounts must already be funded.
+	#[arg(long)]
+	ws_url: Vec<String>,
+
+	/// Path to relay chain node binary. Defaults to `polkadot`.
+	#[arg(long)]
+	relay_bin: Option<String>,
+
+	/// Args for relay chain binary, as a comma-separated list of `name=value` or `name`.
 	#[arg(long)]
 	relay_args: Option<String>,
 
-	/// Name of the relay chain spec.
-	#[arg(long, default_value = "rococo-local")]
-	relay_chain: String,
+	/// Name of the relay chain spec. Defaults to `rococo-local`.
+	#[arg(long)]
+	relay_chain: Option<String>,
 
 	/// Path to a custom relay chain spec.
 	#[arg(long)]
@@ -94,29 +186,41 @@ struct Args {
 	#[arg(long)]
 	relay_chainspec_command: Option<String>,
 
-	/// Number of validators.
-	#[arg(long, default_value_t = 2_usize)]
-	relay_nodes: usize,
+	/// Path to a JSON file with relay chain genesis overrides, deep-merged over the default
+	/// `HostConfiguration` override and the generated balances of the benchmark accounts.
+	#[arg(long)]
+	relay_genesis_override: Option<String>,
 
-	/// Perform TPS benchmark on parachain instead of relay chain.
-	#[arg(long, short)]
-	para: bool,
+	/// Number of validators. Defaults to 2.
+	#[arg(long)]
+	relay_nodes: Option<usize>,
 
-	/// Parachain id.
-	#[arg(long, default_value_t = 100_u32)]
-	para_id: u32,
+	/// Perform TPS benchmark on parachain instead of relay chain. `--para=false` benchmarks the
+	/// relay chain of a scenario with parachains.
+	#[arg(long, short, num_args = 0..=1, default_missing_value = "true")]
+	para: Option<bool>,
 
-	/// Path to parachain collator binary.
-	#[arg(long, default_value = "polkadot-parachain")]
-	para_bin: String,
+	/// Number of parachains to spawn and benchmark simultaneously. They are all configured like the
+	/// first parachain of the scenario, with consecutive ids. Implies `--para`.
+	#[arg(long)]
+	paras: Option<usize>,
+
+	/// Parachain id. Defaults to 100. With several parachains, the following ones get consecutive
+	/// ids.
+	#[arg(long)]
+	para_id: Option<u32>,
 
-	/// Args for parachain collator binary.
+	/// Path to parachain collator binary. Defaults to `polkadot-parachain`.
+	#[arg(long)]
+	para_bin: Option<String>,
+
+	/// Args for parachain collator binary, as a comma-separated list of `name=value` or `name`.
 	#[arg(long)]
 	para_args: Option<String>,
 
-	/// Number of collators
-	#[arg(long, default_value_t = 1_usize)]
-	para_nodes: usize,
+	/// Number of collators. Defaults to 1.
+	#[arg(long)]
+	para_nodes: Option<usize>,
 
 	/// Name of the parachain spec or path to the spec file. If not specified, defaults
 	/// to the default chain spec of the collator.
@@ -131,21 +235,162 @@ struct Args {
 	#[arg(long)]
 	para_chainspec_command: Option<String>,
 
-	/// Block height to wait for before starting the benchmark
-	#[arg(long, short, default_value_t = 5_usize)]
-	block_height: usize,
+	/// Path to a JSON file with parachain genesis overrides, deep-merged with the generated
+	/// balances of the benchmark accounts.
+	#[arg(long)]
+	para_genesis_override: Option<String>,
 
-	/// Keep the network running after the benchmark is finished until it's interrupted manually
+	/// Block height to wait for before starting the benchmark. Defaults to 5.
 	#[arg(long, short)]
-	keep: bool,
+	block_height: Option<usize>,
+
+	/// Keep the network running after the benchmark is finished until it's interrupted manually.
+	/// `--keep=false` overrides the scenario.
+	#[arg(long, short, num_args = 0..=1, default_missing_value = "true")]
+	keep: Option<bool>,
+
+	/// Also measure the parachains through the relay chain: the parablocks included by the
+	/// `ParaInclusion::CandidateIncluded` events of the finalized relay chain blocks are scraped
+	/// from the collator, reporting their inclusion latency and TPS per relay chain block.
+	/// `--para-finality=false` overrides the scenario.
+	#[arg(long, num_args = 0..=1, default_missing_value = "true")]
+	para_finality: Option<bool>,
+
+	/// Deadline of the benchmark, in seconds from the start of the first run. When it is reached,
+	/// the run stops and reports how many transactions were submitted, not submitted, included,
+	/// dropped, invalid or still unaccounted for, and `stps` exits with code 2.
+	#[arg(long)]
+	timeout: Option<u64>,
 
 	/// Prometheus Listener URL
 	#[arg(long)]
 	prometheus_url: Option<String>,
 
-	/// Prometheus Listener Port
-	#[arg(long, default_value_t = 65432)]
-	prometheus_port: u16,
+	/// Prometheus Listener Port. Defaults to 65432.
+	#[arg(long)]
+	prometheus_port: Option<u16>,
+}
+
+impl Args {
+	/// Loads the scenario file, if any, and applies the command line overrides on top of it.
+	fn into_scenario(self) -> Result<Scenario, Box<dyn Error>> {
+		let mut s = if let Some(path) = &self.scenario {
+			Scenario::load(path)?
+		} else {
+			Scenario::default()
+		};
+
+		let w = &mut s.workload;
+		override_with(&mut w.ss58_prefix, self.ss58_prefix);
+		override_with(&mut w.threads, self.threads);
+		override_with(&mut w.count, self.count);
+		override_with(&mut w.mode, self.mode);
+		if self.mix.is_some() {
+			w.mode = BenchMode::Mix;
+			w.mix = self.mix;
+		}
+		if self.accounts.is_some() {
+			w.accounts = self.accounts;
+		}
+		override_with(&mut w.txs_per_account, self.txs_per_account);
+		if self.key_type.is_some() {
+			w.key_type = self.key_type;
+		}
+		override_with(&mut w.tip, self.tip);
+		if self.mortality.is_some() {
+			w.mortality = self.mortality;
+		}
+		override_with(&mut w.asset_id, self.asset_id);
+		override_with(&mut w.asset_sufficient, self.asset_sufficient);
+		override_with(&mut w.xcm_transfer, self.xcm_transfer);
+		override_with(&mut w.xcm_direction, self.xcm_direction);
+		if self.contract_code.is_some() {
+			w.contract_code = self.contract_code;
+		}
+		if !self.remark_size.is_empty() {
+			w.remark_sizes = self.remark_size;
+		}
+		override_with(&mut w.remark_call, self.remark_call);
+
+		let t = &mut s.topology;
+		if !self.ws_url.is_empty() {
+			t.attach = self.ws_url;
+		}
+
+		let r = &mut t.relay;
+		override_with(&mut r.bin, self.relay_bin);
+		override_with(&mut r.args, self.relay_args.as_deref().map(split_args));
+		override_with(&mut r.chain, self.relay_chain);
+		if self.relay_chainspec.is_some() {
+			r.chainspec = self.relay_chainspec;
+		}
+		if self.relay_chainspec_command.is_some() {
+			r.chainspec_command = self.relay_chainspec_command;
+		}
+		if self.relay_genesis_override.is_some() {
+			r.genesis_override = self.relay_genesis_override;
+		}
+		override_with(&mut r.nodes, self.relay_nodes);
+
+		if let Some(n) = self.paras {
+			if self.para == Some(false) {
+				return Err("--paras implies --para".into());
+			}
+			if n == 0 {
+				return Err(
+					"--paras must be at least 1; omit it to benchmark the relay chain".into()
+				);
+			}
+			let template = t.paras.first().cloned().unwrap_or_default();
+			t.paras = (0..n as u32)
+				.map(|i| Para { id: template.id + i, ..template.clone() })
+				.collect();
+		} else {
+			match self.para {
+				Some(true) if t.paras.is_empty() => t.paras.push(Default::default()),
+				Some(false) => t.paras.clear(),
+				_ => (),
+			}
+		}
+		for (i, p) in t.paras.iter_mut().enumerate() {
+			override_with(&mut p.id, self.para_id.map(|id| id + i as u32));
+			override_with(&mut p.bin, self.para_bin.clone());
+			override_with(&mut p.args, self.para_args.as_deref().map(split_args));
+			override_with(&mut p.nodes, self.para_nodes);
+			if self.para_chain.is_some() {
+				p.chain = self.para_chain.clone();
+			}
+			if self.para_chainspec.is_some() {
+				p.chainspec = self.para_chainspec.clone();
+			}
+			if self.para_chainspec_command.is_some() {
+				p.chainspec_command = self.para_chainspec_command.clone();
+			}
+			if self.para_genesis_override.is_some() {
+				p.genesis_override = self.para_genesis_override.clone();
+			}
+		}
+
+		let m = &mut s.measurement;
+		override_with(&mut m.block_height, self.block_height);
+		override_with(&mut m.keep, self.keep);
+		override_with(&mut m.para_finality, self.para_finality);
+		if self.timeout.is_some() {
+			m.timeout = self.timeout;
+		}
+		if self.prometheus_url.is_some() {
+			m.prometheus_url = self.prometheus_url;
+		}
+		override_with(&mut m.prometheus_port, self.prometheus_port);
+
+		Ok(s)
+	}
+}
+
+fn override_with<T>(value: &mut T, arg: Option<T>) {
+	if let Some(arg) = arg {
+		*value = arg;
+	}
 }
 
 async fn wait_for_metric(
@@ -179,173 +424,30 @@ async fn wait_for_metric(
 	.await?
 }
 
-#[derive(Decode)]
-struct Collection {
-	clid: u32,
-	owner: [u8; 32],
-}
-
-#[derive(Decode)]
-enum FinalizedEvent {
-	NftCollectionCreated(Collection),
-	NftMinted,
-}
-
-async fn block_subscriber(
-	api: OnlineClient<PolkadotConfig>,
-	ntrans: usize,
-	coll_sender: Option<UnboundedSender<FinalizedEvent>>,
-	metrics: Option<StpsMetrics>,
-) -> Result<(), subxt::Error> {
-	let mut blocks_sub = api.blocks().subscribe_finalized().await?;
-
-	let mut last_block_timestamp = 0;
-	let mut total_blocktime = 0;
-	let mut total_ntrans = 0;
-	let mut _first_tran_timestamp = 0;
-	let mut max_trans = 0;
-	let mut max_tps = 0.0;
-	log::debug!("Starting chain watcher");
-	while let Some(block) = blocks_sub.next().await {
-		let block = block?;
-		let mut last_block_ntrans = 0;
-		let mut last_blocktime: u64 = 0;
-
-		for ex in block.extrinsics().await?.iter() {
-			match (ex.pallet_name()?, ex.variant_name()?) {
-				("Timestamp", "set") => {
-					let timestamp: Compact<u64> = Decode::decode(&mut &ex.field_bytes()[..])?;
-					let timestamp = u64::from(timestamp);
-					last_blocktime = timestamp - last_block_timestamp;
-					if total_ntrans == 0 {
-						_first_tran_timestamp = timestamp;
-					}
-					last_block_timestamp = timestamp;
-				},
-				("Balances", "transfer_keep_alive") | ("Nfts", "transfer") => {
-					last_block_ntrans += 1;
-				},
-				_ => (),
-			}
-		}
-
-		let mut proc_coll = 0;
-		let mut proc_mint = 0;
-		for ev in block.events().await?.iter() {
-			let ev = ev?;
-			// log::info!("EVENT {}::{}", ev.pallet_name(), ev.variant_name());
-			match (ev.pallet_name(), ev.variant_name()) {
-				("Nfts", "Created") => {
-					proc_coll += 1;
-					if let Some(ref sender) = coll_sender {
-						let b = ev.field_bytes();
-						let c = Collection::decode(&mut &b[..])?;
-						sender.send(FinalizedEvent::NftCollectionCreated(c)).expect("Sender sends");
-					}
-				},
-				("Nfts", "Issued") => {
-					proc_mint += 1;
-					if let Some(ref sender) = coll_sender {
-						sender.send(FinalizedEvent::NftMinted).expect("Sender sends");
-					}
-				},
-				_ => (),
-			}
-		}
-
-		if last_block_ntrans > 0 {
-			log::debug!(
-				"Last block time {last_blocktime}, {last_block_ntrans} transactions in block"
-			);
-			total_blocktime += last_blocktime;
-			total_ntrans += last_block_ntrans;
-			max_trans = max(max_trans, last_block_ntrans);
-			let block_tps = last_block_ntrans as f64 / (last_blocktime as f64 / 1_000_f64);
-			max_tps = f64::max(max_tps, block_tps);
-			log::info!("TPS in block: {:?}", block_tps);
-			log::info!(
-				"TPS average: {}",
-				total_ntrans as f64 / (total_blocktime as f64 / 1_000_f64)
-			);
-			log::info!("Max TPS: {max_tps}, max transactions per block {max_trans}");
-			if let Some(ref metrics) = metrics {
-				metrics.set(last_block_ntrans, last_blocktime, block.number());
-			}
-		}
-
-		if proc_coll > 0 {
-			log::info!("Created NFT collections in block: {proc_coll}");
-		}
-
-		if proc_mint > 0 {
-			log::info!("Minted NFTs in block: {proc_mint}");
-		}
-
-		log::info!("Total transactions processed: {total_ntrans}");
-
-		if total_ntrans >= ntrans as u64 {
-			break;
-		}
-	}
-	Ok(())
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-	env_logger::init_from_env(
-		env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "info"),
-	);
-
-	let args = Args::parse();
-
-	let nthreads = if args.threads == 0 {
-		std::thread::available_parallelism().unwrap_or(1usize.try_into().unwrap()).get()
-	} else {
-		args.threads
-	};
-	let ntrans = nthreads * args.count;
-
-	let naccs = if let Some(accounts) = args.accounts {
-		assert!(
-			accounts >= ntrans,
-			"Number of accounts specified is less than the number of transactions"
-		);
-		accounts
-	} else {
-		ntrans
-	};
-
-	let mut send_accs: Vec<_> = funder_lib::derive_accounts(naccs, SENDER_SEED.to_owned());
-	let mut recv_accs: Vec<_> = funder_lib::derive_accounts(naccs, RECEIVER_SEED.to_owned());
-
-	let accs = send_accs
-		.iter()
-		.chain(recv_accs.iter())
-		.map(|p: &SrPair| (p.public().to_ss58check_with_version(args.ss58_prefix.into()), FUNDS))
-		.collect::<Vec<_>>();
-
-	let genesis_accs = json!({ "balances": { "balances": &serde_json::to_value(accs)? } });
-
-	send_accs.truncate(ntrans);
-	recv_accs.truncate(ntrans);
-
+/// Spawns a native zombienet network with the given topology, applying the `relay_genesis`
+/// override to the relay chain and the `para_genesis` overrides to the respective parachains.
+async fn spawn_network(
+	topology: &Topology,
+	relay_genesis: serde_json::Value,
+	para_genesis: Vec<serde_json::Value>,
+) -> Result<Network<LocalFileSystem>, Box<dyn Error>> {
+	let relay = &topology.relay;
 	let mut relay_hostname = HostnameGen::new("validator");
 
-	let relay = NetworkConfigBuilder::new().with_relaychain(|r| {
-		let r = r.with_chain(args.relay_chain.as_str());
-		let r = if let Some(chainspec) = args.relay_chainspec {
+	let network = NetworkConfigBuilder::new().with_relaychain(|r| {
+		let r = r.with_chain(relay.chain.as_str());
+		let r = if let Some(chainspec) = &relay.chainspec {
 			r.with_chain_spec_path(chainspec.as_str())
-		} else if let Some(chainspec_command) = args.relay_chainspec_command {
-			r.with_chain_spec_command(chainspec_command)
+		} else if let Some(chainspec_command) = &relay.chainspec_command {
+			r.with_chain_spec_command(chainspec_command.as_str())
 		} else {
 			r
 		};
-		let r = r.with_default_command(args.relay_bin.as_str());
+		let r = r.with_default_command(relay.bin.as_str());
 
-		let r = if let Some(relay_args) = args.relay_args {
-			let pairs: Vec<_> = relay_args.split(',').collect();
+		let r = if !relay.args.is_empty() {
 			let mut a = Vec::new();
-			for p in pairs {
+			for p in &relay.args {
 				a.push(if p.contains('=') {
 					let pv: Vec<_> = p.splitn(2, '=').collect();
 					(format!("--{}", pv[0]).as_str(), pv[1]).into()
@@ -358,40 +460,40 @@ async fn main() -> Result<(), Box<dyn Error>> {
 			r
 		};
 
-		let r = r.with_genesis_overrides(json!({ "configuration": { "config": { "executor_params": [ { "MaxMemoryPages": 8192 }, { "PvfExecTimeout": [ "Backing", 2500 ] } ] } } } ));
+		let r = r.with_genesis_overrides(relay_genesis);
 
-		let mut r = r.with_node(|node| node.with_name(relay_hostname.next().as_str()).invulnerable(true));
+		let mut r =
+			r.with_node(|node| node.with_name(relay_hostname.next().as_str()).invulnerable(true));
 
-		for _ in 1..args.relay_nodes {
-			r = r.with_node(|node| node.with_name(relay_hostname.next().as_str()).invulnerable(true));
+		for _ in 1..relay.nodes {
+			r = r.with_node(|node| {
+				node.with_name(relay_hostname.next().as_str()).invulnerable(true)
+			});
 		}
 
 		r
 	});
 
-	let network = if !args.para {
-		relay
-	} else {
-		let mut para_hostname = HostnameGen::new("collator");
-		relay.with_parachain(|p| {
-			let p = p.with_id(args.para_id).with_default_command(args.para_bin.as_str());
+	let mut network = network;
+	for (para, para_genesis) in topology.paras.iter().zip(para_genesis) {
+		let mut para_hostname = HostnameGen::new(collator_prefix(para.id));
+		network = network.with_parachain(|p| {
+			let p = p.with_id(para.id).with_default_command(para.bin.as_str());
 			//.with_chain_spec_command("{{mainCommand}} build-spec --extra-heap-pages 65000 --chain {{chainName}} {{disableBootnodes}}");
 
-			let p =
-				if let Some(chain) = args.para_chain { p.with_chain(chain.as_str()) } else { p };
+			let p = if let Some(chain) = &para.chain { p.with_chain(chain.as_str()) } else { p };
 
-			let p = if let Some(chainspec) = args.para_chainspec {
+			let p = if let Some(chainspec) = &para.chainspec {
 				p.with_chain_spec_path(chainspec.as_str())
-			} else if let Some(chainspec_command) = args.para_chainspec_command {
-				p.with_chain_spec_command(chainspec_command)
+			} else if let Some(chainspec_command) = &para.chainspec_command {
+				p.with_chain_spec_command(chainspec_command.as_str())
 			} else {
 				p
 			};
 
-			let p = if let Some(para_args) = args.para_args {
-				let pairs: Vec<_> = para_args.split(',').collect();
+			let p = if !para.args.is_empty() {
 				let mut a = Vec::new();
-				for p in pairs {
+				for p in &para.args {
 					a.push(if p.contains('=') {
 						let pv: Vec<_> = p.splitn(2, '=').collect();
 						(format!("--{}", pv[0]).as_str(), pv[1]).into()
@@ -408,36 +510,29 @@ async fn main() -> Result<(), Box<dyn Error>> {
 			let mut p = p
 				.cumulus_based(true)
 				.with_registration_strategy(RegistrationStrategy::InGenesis)
-				.with_genesis_overrides(genesis_accs)
+				.with_genesis_overrides(para_genesis)
 				.with_collator(|n| n.with_name(para_hostname.next().as_str()));
 
-			for _ in 1..args.para_nodes {
+			for _ in 1..para.nodes {
 				p = p.with_collator(|n| n.with_name(para_hostname.next().as_str()));
 			}
 
 			p
-		})
-	};
-
-	let network = network.build().unwrap();
-	let network = network.spawn_native().await?;
-
-	let metrics = if let Some(url) = args.prometheus_url {
-		Some(run_prometheus_endpoint(&url, &args.prometheus_port).await?)
-	} else {
-		None
-	};
-
-	let node = network.get_node(if args.para { "collator01" } else { "validator01" })?;
+		});
+	}
 
-	wait_for_metric(node, "block_height{status=\"best\"}", Duration::from_secs(300), |bh| {
-		bh >= args.block_height as f64
-	})
-	.await?;
+	Ok(network.build().unwrap().spawn_native().await?)
+}
 
-	log::info!("Block height reached");
+/// Name prefix of the collators of the given parachain, so that they are unique across the network.
+fn collator_prefix(para_id: u32) -> String {
+	format!("collator{para_id}-")
+}
 
-	let node_url = url::Url::parse(node.ws_uri())?;
+/// Connects to the node at `url` with limits suitable for submitting a large number of transactions
+/// at once.
+async fn connect(url: &str) -> Result<OnlineClient<PolkadotConfig>, Box<dyn Error>> {
+	let node_url = url::Url::parse(url)?;
 	let (node_sender, node_receiver) = WsTransportClientBuilder::default().build(node_url).await?;
 	let client = Client::builder()
 		.request_timeout(Duration::from_secs(3600))
@@ -445,173 +540,1135 @@ async fn main() -> Result<(), Box<dyn Error>> {
 		.max_concurrent_requests(2 * 1024 * 1024)
 		.build_with_tokio(node_sender, node_receiver);
 	let backend = LegacyBackend::builder().build(client);
-	let api = OnlineClient::from_backend(Arc::new(backend)).await?;
+	Ok(OnlineClient::from_backend(Arc::new(backend)).await?)
+}
+
+/// Reads `System::Account` of every account at the latest finalized block and returns their nonces.
+/// Fails if any of the accounts has no free balance, as the benchmark cannot run against an attached
+/// network without the accounts being funded beforehand.
+async fn check_funded(
+	api: &OnlineClient<PolkadotConfig>,
+	accounts: &[Keypair],
+	ss58_prefix: u16,
+) -> Result<Vec<u64>, Box<dyn Error>> {
+	let storage = api.storage().at_latest().await?;
+	let infos = futures::stream::iter(accounts.iter().map(|acc| {
+		let storage = storage.clone();
+		let addr = subxt::dynamic::storage(
+			"System",
+			"Account",
+			vec![TxValue::from_bytes(acc.account_id())],
+		);
+		async move {
+			let info = storage.fetch(&addr).await?.map(|info| info.to_value()).transpose()?;
+			Ok::<_, subxt::Error>(info.map(|info| {
+				let nonce = info.at("nonce").and_then(|n| n.as_u128()).unwrap_or_default();
+				let free = info.at("data").at("free").and_then(|f| f.as_u128()).unwrap_or_default();
+				(nonce as u64, free)
+			}))
+		}
+	}))
+	.buffered(256)
+	.try_collect::<Vec<_>>()
+	.await?;
+
+	let unfunded = accounts
+		.iter()
+		.zip(infos.iter())
+		.filter(|(_, info)| !matches!(info, Some((_, free)) if *free > 0))
+		.map(|(acc, _)| acc.address(ss58_prefix))
+		.collect::<Vec<_>>();
+
+	if !unfunded.is_empty() {
+		return Err(format!(
+			"{} of {} benchmark accounts are not funded on the attached network, e.g. {}",
+			unfunded.len(),
+			accounts.len(),
+			unfunded[0]
+		)
+		.into());
+	}
+
+	Ok(infos
+		.into_iter()
+		.map(|info| info.map(|(nonce, _)| nonce).unwrap_or_default())
+		.collect())
+}
+
+/// Spreads signed transactions round-robin across the given clients so that each node receives
+/// its share of the load.
+fn spread_txs(
+	txs: Vec<sender_lib::SignedTx>,
+	apis: &[OnlineClient<PolkadotConfig>],
+) -> Vec<sender_lib::SignedTx> {
+	if apis.len() < 2 {
+		return txs;
+	}
+	txs.into_iter()
+		.enumerate()
+		.map(|(i, tx)| {
+			SubmittableTransaction::from_bytes(apis[i % apis.len()].clone(), tx.into_encoded())
+		})
+		.collect()
+}
+
+/// Assigns `n` items to classes in proportion to their `weights`, spreading every class as evenly
+/// as possible across the items, with the smooth weighted round-robin algorithm.
+fn interleave(weights: &[u32], n: usize) -> Vec<usize> {
+	let total = weights.iter().map(|w| *w as i64).sum::<i64>();
+	let mut current = vec![0i64; weights.len()];
+	(0..n)
+		.map(|_| {
+			current.iter_mut().zip(weights).for_each(|(c, w)| *c += *w as i64);
+			let (class, _) = current
+				.iter()
+				.enumerate()
+				.max_by_key(|(i, c)| (**c, std::cmp::Reverse(*i)))
+				.expect("There is at least one class");
+			current[class] -= total;
+			class
+		})
+		.collect()
+}
+
+/// Awaits a future, unless the deadline is reached first.
+async fn until<T>(fut: impl Future<Output = T>, deadline: Option<Instant>) -> Option<T> {
+	match deadline {
+		Some(deadline) => tokio::time::timeout_at(deadline.into(), fut).await.ok(),
+		None => Some(fut.await),
+	}
+}
+
+/// Joins a task, unless the deadline is reached first, in which case it is aborted.
+async fn join_until<T>(
+	mut task: JoinHandle<T>,
+	deadline: Option<Instant>,
+) -> Result<Option<T>, JoinError> {
+	let res = until(&mut task, deadline).await;
+	if res.is_none() {
+		task.abort();
+	}
+	res.transpose()
+}
+
+/// A chain to run the benchmark against.
+#[derive(Clone)]
+struct Target {
+	chain: String,
+	/// WebSocket URLs of the nodes to send the transactions to.
+	urls: Vec<String>,
+	/// `(chain, url)` of the destination of cross-chain transfers.
+	delivery: Option<(String, String)>,
+	/// Relay chain URL and `(sender, recipient)` parachain ids of the HRMP channel carrying the
+	/// cross-chain transfers.
+	hrmp: Option<(String, (u32, u32))>,
+	/// Relay chain URL and parachain id, to measure the parachain through the inclusion of its
+	/// candidates.
+	inclusion: Option<(String, u32)>,
+}
+
+/// Runs the benchmark against a single chain and returns its TPS figures. `ntrans` transactions are
+/// sent, up to `txs_per_account` of them by every sender with consecutive nonces. They are
+/// spread across all the nodes of the target, while the blocks are scraped from the first one. For
+/// cross-chain workloads, the messages are tracked on the destination chain, and on the relay chain
+/// when they go over HRMP. Parachains may also be measured through the relay chain blocks including
+/// them. Once the deadline is reached, the run stops with the figures collected so far.
+#[allow(clippy::too_many_arguments)]
+async fn run_benchmark(
+	target: Target,
+	ntrans: usize,
+	check_accounts: bool,
+	topology: &Topology,
+	workload: &Workload,
+	send_accs: Vec<Keypair>,
+	recv_accs: Vec<Keypair>,
+	deadline: Option<Instant>,
+	metrics: Option<StpsMetrics>,
+) -> Result<TpsSummary, Box<dyn Error>> {
+	let Target { chain, urls, delivery, hrmp, inclusion } = target;
+	let nsenders = send_accs.len();
+
+	// An unresponsive node would keep the run from ever reaching the deadline otherwise.
+	let connecting = async {
+		let apis = futures::future::try_join_all(urls.iter().map(|url| connect(url))).await?;
+		let dest_api = match &delivery {
+			Some((_, url)) => Some(connect(url).await?),
+			None => None,
+		};
+		let hrmp_api = match &hrmp {
+			Some((url, _)) => Some(connect(url).await?),
+			None => None,
+		};
+		let inclusion_api = match &inclusion {
+			Some((url, _)) => Some(connect(url).await?),
+			None => None,
+		};
+		Ok::<_, Box<dyn Error>>((apis, dest_api, hrmp_api, inclusion_api))
+	};
+	let Some((apis, dest_api, hrmp_api, inclusion_api)) =
+		until(connecting, deadline).await.transpose()?
+	else {
+		log::warn!("[{chain}] Deadline reached while connecting to the nodes");
+		return Ok(unsent_summary(ntrans));
+	};
+	let api = apis[0].clone();
 
 	// When using local senders, it is okay to skip pre-conditions check as we've just generated
-	// everything ourselves
+	// everything ourselves. An attached network may be in any state, so the accounts must be
+	// checked, and the senders' nonces may have advanced during previous runs, as they do between
+	// the runs of a sweep.
+	let nonces = if check_accounts {
+		log::info!("[{chain}] Checking that benchmark accounts are funded...");
+		let checking = async {
+			let nonces = check_funded(&api, &send_accs, workload.ss58_prefix).await?;
+			if workload.mode != BenchMode::FreshTransfer {
+				check_funded(&api, &recv_accs, workload.ss58_prefix).await?;
+			}
+			Ok::<_, Box<dyn Error>>(nonces)
+		};
+		let Some(nonces) = until(checking, deadline).await.transpose()? else {
+			log::warn!("[{chain}] Deadline reached while checking the benchmark accounts");
+			return Ok(unsent_summary(ntrans));
+		};
+		nonces
+	} else {
+		vec![0; send_accs.len()]
+	};
+	let mut send_accs = send_accs.into_iter().zip(nonces).collect::<Vec<_>>();
 
-	let (coll_send, mut coll_recv) = mpsc::unbounded_channel();
+	let (classes, weights): (Vec<_>, Vec<_>) = workload.classes(topology)?.into_iter().unzip();
+	for class in &classes {
+		class.workload.prepare(&api)?;
+	}
+	let sender_classes = interleave(&weights, nsenders);
+	let (setup_send, mut setup_recv) = mpsc::unbounded_channel();
+
+	// Number of messages sent by the cross-chain transfers, i.e. of those dispatched successfully,
+	// once they have all been counted.
+	let (nmsgs_send, nmsgs_recv) = watch::channel(None);
+	// Number of messages processed by the destination chain so far.
+	let (delivered_send, delivered_recv) = watch::channel(0);
+	let delivery = match delivery.zip(dest_api) {
+		Some(((dest, _), dest_api)) => {
+			let nmsgs = nmsgs_recv.clone();
+			Some(tokio::spawn(message_subscriber(dest, dest_api, nmsgs, delivered_send)))
+		},
+		None => None,
+	};
+	let hrmp = match hrmp.zip(hrmp_api) {
+		Some(((_, channel), relay_api)) => {
+			let metrics = metrics.clone();
+			let nmsgs = nmsgs_recv.clone();
+			Some(tokio::spawn(hrmp_sampler(
+				"relay".into(),
+				relay_api,
+				channel,
+				nmsgs,
+				delivered_recv,
+				metrics,
+			)))
+		},
+		None => None,
+	};
 
-	let sub_api = api.clone();
-	let subscriber = tokio::spawn(async move {
-		match block_subscriber(sub_api, ntrans, Some(coll_send), metrics).await {
-			Ok(()) => {
-				log::debug!("Block subscriber exited");
-			},
-			Err(e) => {
-				log::error!("Block subscriber exited with error: {:?}", e);
-			},
-		}
-	});
+	let (lost_send, lost_recv) = watch::channel(0);
+	let subscriber = tokio::spawn(block_subscriber(
+		chain.clone(),
+		api.clone(),
+		classes.clone(),
+		ntrans,
+		lost_recv,
+		deadline,
+		Some(setup_send),
+		metrics.clone(),
+	));
+	let (stop_send, stop_recv) = oneshot::channel();
+	// Number of the best block, to tell the mortal transactions dropped as their era ended.
+	let (best_send, best_recv) = watch::channel(0);
+	let finality = tokio::spawn(finality_tracker(
+		chain.clone(),
+		api.clone(),
+		classes.clone(),
+		best_send,
+		stop_recv,
+	));
+	let inclusion = match inclusion.zip(inclusion_api) {
+		Some(((_, para_id), relay_api)) => {
+			let (stop_send, stop_recv) = oneshot::channel();
+			let subscriber = tokio::spawn(inclusion_subscriber(
+				chain.clone(),
+				relay_api,
+				api.clone(),
+				para_id,
+				classes.clone(),
+				stop_recv,
+			));
+			Some((stop_send, subscriber))
+		},
+		None => None,
+	};
 
-	log::info!("Signing {} transactions...", send_accs.len());
-	let txs = match args.mode {
-		BenchMode::Stps => sender_lib::sign_balance_transfers(
-			api.clone(),
-			send_accs.into_iter().map(|a| (a, 0)).zip(recv_accs.into_iter()),
-		),
-		BenchMode::NftTransfer => {
-			let api2 = api.clone();
-			let create_coll_txs = sender_lib::sign_txs::<_, _, PolkadotConfig>(
-				send_accs.clone().into_iter(),
-				move |sender| {
-					let tx_params = DefaultExtrinsicParamsBuilder::new().nonce(0).build();
-					let tx_call = subxt::dynamic::tx(
-						"Nfts",
-						"create",
-						vec![
-							TxValue::unnamed_variant("Id", [TxValue::from_bytes(sender.public())]),
-							TxValue::named_composite(vec![
-								("settings", TxValue::primitive(0u64.into())),
-								("max_supply", TxValue::unnamed_variant("None", vec![])),
-								(
-									"mint_settings",
-									TxValue::named_composite(vec![
-										("mint_type", TxValue::unnamed_variant("Issuer", vec![])),
-										("price", TxValue::unnamed_variant("None", vec![])),
-										("start_block", TxValue::unnamed_variant("None", vec![])),
-										("end_block", TxValue::unnamed_variant("None", vec![])),
-										("default_item_settings", TxValue::primitive(0u64.into())),
-									]),
-								),
-							]),
-						],
-					);
-					api2.tx()
-						.create_partial_offline(&tx_call, tx_params)
-						.expect("Failed to create partial offline transaction")
-						.sign(&PairSigner::new(sender))
-				},
-			);
-			let futs =
-				create_coll_txs.iter().map(|tx| tx.submit()).collect::<FuturesUnordered<_>>();
-			let _res = futs
-				.collect::<Vec<_>>()
-				.await
+	// The setup transactions of every sender take its next nonces, so its measured transactions
+	// come after all of them. Once the deadline is reached, nothing is sent anymore.
+	let mut timed_out = false;
+	'setup: for (c, class) in classes.iter().enumerate() {
+		let senders = (0..nsenders).filter(|i| sender_classes[*i] == c).collect::<Vec<_>>();
+		let ids = senders.iter().map(|i| send_accs[*i].0.account_id()).collect::<Vec<_>>();
+		for (phase, setup) in class.workload.setup_phases().iter().enumerate() {
+			log::info!("[{chain}] Setup phase {} of {}: {}", phase + 1, class.name, setup.name);
+			let calls = class
+				.workload
+				.setup_calls(phase, &ids)
 				.into_iter()
-				.collect::<Result<Vec<_>, _>>()
-				.expect("All the transactions submitted successfully");
-
-			let mut proc_coll = 0;
-			let mut map: HashMap<[u8; 32], u32> = HashMap::new();
-			while proc_coll < ntrans {
-				let e = coll_recv.recv().await.expect("Recv receives");
-				let FinalizedEvent::NftCollectionCreated(c) = e else {
-					panic!("Unexpected event");
-				};
-				map.insert(c.owner, c.clid);
-				proc_coll += 1;
+				.map(|(signer, call)| {
+					let (sender, nonce) = &mut send_accs[senders[signer]];
+					*nonce += 1;
+					((sender.clone(), *nonce - 1), call)
+				})
+				.collect::<Vec<_>>();
+			let setup_txs = sender_lib::sign_setup_txs(api.clone(), calls.into_iter());
+			let run_phase = async {
+				let futs = setup_txs.iter().map(|tx| tx.submit_and_watch());
+				let progress = futures::future::try_join_all(futs).await?;
+				// A setup transaction which fails never emits the event the phase waits for.
+				let mut outcomes = progress
+					.into_iter()
+					.map(|p| p.wait_for_finalized_success())
+					.collect::<FuturesUnordered<_>>();
+
+				for _ in 0..setup_txs.len() {
+					let event = tokio::select! {
+						event = setup_recv.recv() => event,
+						Some(Err(e)) = outcomes.next() => {
+							return Err(format!(
+								"Setup phase {} of {} failed: {e}",
+								phase + 1,
+								class.name
+							)
+							.into());
+						},
+					};
+					let event = event.ok_or("Block subscriber exited during setup")?;
+					if (event.class, event.phase) != (c, phase) {
+						return Err(format!(
+							"Unexpected event of setup phase {} of {}",
+							event.phase + 1,
+							classes[event.class].name
+						)
+						.into());
+					}
+					class.workload.on_setup_event(phase, &event.fields)?;
+				}
+				Ok::<_, Box<dyn Error>>(())
+			};
+			if until(run_phase, deadline).await.transpose()?.is_none() {
+				log::warn!(
+					"[{chain}] Deadline reached during setup phase {} of {}",
+					phase + 1,
+					class.name
+				);
+				timed_out = true;
+				break 'setup;
 			}
+		}
+	}
 
-			let mut cll = Vec::new();
+	// The transactions are signed in order and their submissions are issued in that order, so
+	// every sender's transactions with a given nonce are sent before the ones with the next nonce,
+	// and as many as possible are ready, while the others queue up in the future pool. The
+	// workload classes are signed separately, then interleaved again in that order, so that the
+	// `j`th transaction is sent by the `j % nsenders`th sender, with the `j % nsenders`th tip.
+	// Every sender sends all its transactions to the same receiver, unless there is a receiver per
+	// transaction.
+	let tips = workload.tip.tips(nsenders);
+	// Mortal transactions are anchored at the best block, as wallets do.
+	let mortality = match workload.mortality {
+		Some(period) if !timed_out => {
+			let best = async {
+				let mut best = api.blocks().subscribe_best().await?;
+				Ok::<_, Box<dyn Error>>(best.next().await.ok_or("Best block subscription ended")??)
+			};
+			match until(best, deadline).await.transpose()? {
+				Some(best) => Some(Mortality {
+					period,
+					block_number: best.number().into(),
+					block_hash: best.hash(),
+				}),
+				None => {
+					log::warn!("[{chain}] Deadline reached while waiting for the best block");
+					timed_out = true;
+					None
+				},
+			}
+		},
+		_ => None,
+	};
+	let times = if timed_out {
+		Vec::new()
+	} else {
+		log::info!("[{chain}] Signing {ntrans} transactions...");
+		let txs_per_account = workload.txs_per_account as u64;
+		let tx_classes = (0..txs_per_account)
+			.flat_map(|_| sender_classes.iter().copied())
+			.take(ntrans)
+			.collect::<Vec<_>>();
+		let (recv_accs, tips) = (&recv_accs, &tips);
+		let mut class_txs = classes
+			.iter()
+			.enumerate()
+			.map(|(c, class)| {
+				let count = tx_classes.iter().filter(|tc| **tc == c).count();
+				let pairs = (0..txs_per_account)
+					.flat_map(|k| {
+						send_accs
+							.iter()
+							.enumerate()
+							.zip(&sender_classes)
+							.filter(move |(_, sc)| **sc == c)
+							.map(move |((i, (sender, nonce)), _)| {
+								let receiver =
+									&recv_accs[(k as usize * nsenders + i) % recv_accs.len()];
+								let params =
+									TxParams { nonce: nonce + k, tip: tips[i].into(), mortality };
+								((sender.clone(), params), receiver.clone())
+							})
+					})
+					.take(count);
+				sender_lib::sign_workload_txs(api.clone(), class.workload.clone(), pairs)
+					.into_iter()
+			})
+			.collect::<Vec<_>>();
+		let txs = tx_classes
+			.iter()
+			.map(|c| class_txs[*c].next().expect("Transactions of every class are signed"))
+			.collect::<Vec<_>>();
+
+		log::info!("[{chain}] Transactions signed");
+
+		log::info!("[{chain}] Sending transactions...");
+		let times = sender_lib::submit_txs(spread_txs(txs, &apis), deadline, &best_recv).await?;
+		log::info!("[{chain}] All sent");
+		times
+	};
+	let lost = times.iter().filter(|t| t.dropped.is_some()).count();
+	if lost > 0 {
+		log::warn!("[{chain}] {lost} transactions were dropped before being finalized");
+	}
+	// The block subscriber may be done already.
+	let _ = lost_send.send(lost as u64);
+
+	let mut summary = subscriber.await??;
+	log::debug!("[{chain}] Block subscriber joined");
+	summary.timed_out |= timed_out;
+	let _ = nmsgs_send.send(Some(summary.ntrans));
+	summary.accounting = account_txs(&times, ntrans);
+	// All the measured transactions are finalized, so the best chain is done with them too, unless
+	// the deadline was reached.
+	let _ = stop_send.send(());
+	summary.finality = Some(finality.await??);
+	log::debug!("[{chain}] Finality tracker joined");
+	if let Some((stop_send, subscriber)) = inclusion {
+		let _ = stop_send.send(());
+		summary.inclusion = Some(subscriber.await??);
+		log::debug!("[{chain}] Candidate inclusion watcher joined");
+	}
 
-			for s in send_accs.clone().into_iter() {
-				let addr: [u8; 32] = s.public().into();
-				let cl = map.get(&addr).expect("Collection exists").clone();
-				cll.push((s, cl));
+	if workload.tip != TipDistribution::default() {
+		summary.tips = tip_buckets(&workload.tip, tips, &times);
+	}
+	summary.stages = stage_latencies(&times);
+	if let Some(ref metrics) = metrics {
+		for times in &times {
+			for stage in TxStage::ALL {
+				if let Some(latency) = times.latency(stage) {
+					metrics.observe_latency(&chain, stage.name(), latency);
+				}
 			}
+		}
+	}
+	if let Some(mortality) = mortality {
+		let (expired, dropped) = mortal_outcomes(&times, mortality.death(), *best_recv.borrow());
+		summary.expired = Some(expired);
+		summary.dropped = Some(dropped);
+	}
 
-			let api2 = api.clone();
-
-			let mint_txs = sender_lib::sign_txs::<_, _, PolkadotConfig>(
-				cll.clone().into_iter(),
-				move |coll| {
-					let tx_params = DefaultExtrinsicParamsBuilder::new().nonce(1).build();
-					let tx_call = subxt::dynamic::tx(
-						"Nfts",
-						"mint",
-						vec![
-							TxValue::primitive(coll.1.into()),
-							TxValue::primitive(0u32.into()),
-							TxValue::unnamed_variant("Id", [TxValue::from_bytes(coll.0.public())]),
-							TxValue::unnamed_variant("None", vec![]),
-						],
-					);
-					api2.tx()
-						.create_partial_offline(&tx_call, tx_params)
-						.expect("Failed to create partial offline transaction")
-						.sign(&PairSigner::new(coll.0))
-				},
-			);
+	if let Some(delivery) = delivery {
+		summary.delivery = join_until(delivery, deadline).await?.transpose()?;
+		if summary.delivery.is_none() {
+			log::warn!("[{chain}] Deadline reached before all the messages were processed");
+			summary.timed_out = true;
+		}
+		log::debug!("[{chain}] Message subscriber joined");
+	}
+	if let Some(hrmp) = hrmp {
+		summary.hrmp = join_until(hrmp, deadline).await?.transpose()?;
+		if summary.hrmp.is_none() {
+			log::warn!("[{chain}] Deadline reached before all the messages were sent over HRMP");
+			summary.timed_out = true;
+		}
+		log::debug!("[{chain}] HRMP sampler joined");
+	}
+
+	Ok(summary)
+}
+
+/// Sorts the inclusion latencies of the transactions into the buckets of the tip distribution, the
+/// `j`th transaction being sent by the `j % tips.len()`th sender.
+fn tip_buckets(tip: &TipDistribution, tips: &[u64], times: &[TxTimes]) -> Vec<TipBucket> {
+	let buckets = tip.buckets();
+	let mut ntrans = vec![0; buckets.len()];
+	let mut latencies = vec![Vec::new(); buckets.len()];
+	for (j, times) in times.iter().enumerate() {
+		let tip = tips[j % tips.len()];
+		if let Some(b) = buckets.iter().position(|&(lo, hi)| (lo..=hi).contains(&tip)) {
+			ntrans[b] += 1;
+			latencies[b].extend(times.inclusion_latency());
+		}
+	}
+	buckets
+		.into_iter()
+		.zip(ntrans)
+		.zip(latencies)
+		.map(|((tips, ntrans), latencies)| TipBucket {
+			tips,
+			ntrans,
+			latencies: Latencies::new(latencies),
+		})
+		.collect()
+}
+
+/// Sorts the transactions by the outcome reported by their status stream. Out of the `ntrans`
+/// transactions of the run, those missing from `times` were never sent.
+fn account_txs(times: &[TxTimes], ntrans: usize) -> TxAccounting {
+	let mut accounting = TxAccounting {
+		not_submitted: ntrans.saturating_sub(times.len()) as u64,
+		..Default::default()
+	};
+	for times in times {
+		accounting.submitted += !(times.rejected || times.pending) as u64;
+		if times.included.is_some() {
+			accounting.included += 1;
+		} else if times.invalid {
+			accounting.invalid += 1;
+		} else if times.dropped.is_some() {
+			accounting.dropped += 1;
+		} else if times.pending {
+			accounting.not_submitted += 1;
+		} else {
+			accounting.unaccounted += 1;
+		}
+	}
+	accounting
+}
 
-			let futs = mint_txs.iter().map(|tx| tx.submit()).collect::<FuturesUnordered<_>>();
-			let _res = futs
+/// Numbers of the mortal transactions which were not included as their era expired, or for another
+/// reason. The era ends at the `death` block, and the transactions still in the pool at the end of
+/// the run have expired if the `best` block has reached it.
+fn mortal_outcomes(times: &[TxTimes], death: u64, best: u64) -> (u64, u64) {
+	let (mut expired, mut dropped) = (0, 0);
+	for times in times.iter().filter(|t| t.included.is_none() && !t.pending) {
+		match times.dropped_at {
+			Some(at) if at >= death => expired += 1,
+			Some(_) => dropped += 1,
+			None if best >= death => expired += 1,
+			None => (),
+		}
+	}
+	(expired, dropped)
+}
+
+/// Summary of a run which reached the deadline before sending any of its `ntrans` transactions.
+fn unsent_summary(ntrans: usize) -> TpsSummary {
+	TpsSummary { timed_out: true, accounting: account_txs(&[], ntrans), ..Default::default() }
+}
+
+/// Latencies of the transactions from their submission to every stage of their status stream,
+/// over the transactions reaching it.
+fn stage_latencies(times: &[TxTimes]) -> Vec<(TxStage, Latencies)> {
+	TxStage::ALL
+		.into_iter()
+		.map(|stage| {
+			let latencies = times.iter().filter_map(|t| t.latency(stage)).collect();
+			(stage, Latencies::new(latencies))
+		})
+		.collect()
+}
+
+/// Logs the figures of a benchmark run on every chain.
+fn report(summaries: &[(String, TpsSummary)]) {
+	for (chain, summary) in summaries {
+		log::info!(
+			"[{chain}] Average TPS: {}, max TPS: {}, {} successful transactions",
+			summary.avg_tps(),
+			summary.max_tps,
+			summary.ntrans
+		);
+		if summary.failed > 0 {
+			let mut errors = summary.errors.clone();
+			errors.sort_by(|a, b| b.1.cmp(&a.1));
+			let errors = errors
+				.iter()
+				.map(|(error, n)| format!("{error} ({n})"))
 				.collect::<Vec<_>>()
-				.await
-				.into_iter()
-				.collect::<Result<Vec<_>, _>>()
-				.expect("All the mint transactions submitted successfully");
-
-			let mut proc_mint = 0;
-			while proc_mint < ntrans {
-				let e = coll_recv.recv().await.expect("Receiver receives");
-				if !matches!(e, FinalizedEvent::NftMinted) {
-					panic!("Unexpected event");
+				.join(", ");
+			log::info!("[{chain}] Failed transactions: {}, {errors}", summary.failed);
+		}
+		if summary.classes.len() > 1 {
+			for (name, ntrans) in &summary.classes {
+				log::info!(
+					"[{chain}] {name}: average TPS: {}, {ntrans} transactions",
+					summary.tps(*ntrans)
+				);
+			}
+		}
+		if summary.weight != (0, 0) {
+			let (ref_time, proof_size) = summary.weight_per_block();
+			log::info!("[{chain}] Weight per block: ref_time {ref_time}, proof_size {proof_size}");
+		}
+		for (name, nevents) in &summary.events {
+			log::info!(
+				"[{chain}] {name}: {nevents} events, {} per block, {} per second",
+				summary.per_block(*nevents),
+				summary.tps(*nevents)
+			);
+		}
+		let (mean, max) = summary.length_usage();
+		log::info!(
+			"[{chain}] {} bytes/s, {} transaction bytes per block, block length {:.1}% of the \
+			 limit on average, {:.1}% at most",
+			summary.bytes_per_sec(),
+			summary.bytes_per_block(),
+			100.0 * mean,
+			100.0 * max
+		);
+		for usage in &summary.block_weights {
+			if usage.consumed == (0, 0) {
+				continue;
+			}
+			let (mean, max) = (usage.mean_usage(summary.nblocks), usage.max_usage());
+			log::info!(
+				"[{chain}] Block weight, {}: ref_time {:.1}% of the limit on average, {:.1}% at \
+				 most, proof_size {:.1}% on average, {:.1}% at most",
+				usage.class,
+				100.0 * mean.0,
+				100.0 * max.0,
+				100.0 * mean.1,
+				100.0 * max.1
+			);
+		}
+		if let Some(finality) = &summary.finality {
+			log::info!(
+				"[{chain}] Best chain TPS: {}, finalized TPS: {}, finality lag mean {:.1} blocks, \
+				 max {} blocks, mean {} ms, max {} ms",
+				finality.best_tps(),
+				finality.finalized_tps(),
+				finality.mean_lag_blocks(),
+				finality.lag_blocks.1,
+				finality.mean_lag_time().as_millis(),
+				finality.lag_time.1.as_millis()
+			);
+		}
+		let accounting = &summary.accounting;
+		log::info!(
+			"[{chain}] Transactions: {} submitted, {} not submitted, {} included, {} dropped, {} \
+			 invalid, {} unaccounted for",
+			accounting.submitted,
+			accounting.not_submitted,
+			accounting.included,
+			accounting.dropped,
+			accounting.invalid,
+			accounting.unaccounted
+		);
+		if summary.timed_out {
+			log::error!("[{chain}] Deadline reached before the end of the benchmark");
+		}
+		if let Some(inclusion) = &summary.inclusion {
+			log::info!(
+				"[{chain}] TPS per relay block: {}, max {}, {} parablocks included, inclusion \
+				 latency mean {} ms, max {} ms",
+				inclusion.avg_tps(),
+				inclusion.max_tps,
+				inclusion.nparablocks,
+				inclusion.mean_latency(),
+				inclusion.latency.1
+			);
+		}
+		if let (Some(expired), Some(dropped)) = (summary.expired, summary.dropped) {
+			log::info!(
+				"[{chain}] Mortal transactions not included: {expired} expired, {dropped} dropped \
+				 for another reason"
+			);
+		}
+		for bucket in &summary.tips {
+			let (lo, hi) = bucket.tips;
+			let tips = if lo == hi { format!("Tip {lo}") } else { format!("Tips {lo}-{hi}") };
+			log::info!(
+				"[{chain}] {tips}: {} transactions, {} included, inclusion latency mean {} ms, p50 \
+				 {} ms, p90 {} ms, max {} ms",
+				bucket.ntrans,
+				bucket.latencies.len(),
+				bucket.latencies.mean().as_millis(),
+				bucket.latencies.percentile(0.5).as_millis(),
+				bucket.latencies.percentile(0.9).as_millis(),
+				bucket.latencies.percentile(1.0).as_millis()
+			);
+		}
+		for (stage, latencies) in &summary.stages {
+			if latencies.is_empty() {
+				continue;
+			}
+			log::info!(
+				"[{chain}] {}: {} transactions, latency p50 {} ms, p90 {} ms, p99 {} ms, max {} ms",
+				stage.name(),
+				latencies.len(),
+				latencies.percentile(0.5).as_millis(),
+				latencies.percentile(0.9).as_millis(),
+				latencies.percentile(0.99).as_millis(),
+				latencies.percentile(1.0).as_millis()
+			);
+		}
+		if let Some(delivery) = &summary.delivery {
+			log::info!(
+				"[{chain}] Received TPS: {}, delivery latency: {} ms, {} messages processed, {} failed",
+				delivery.avg_tps(),
+				delivery.mean_processed_at() - summary.mean_included_at(),
+				delivery.processed,
+				delivery.failed
+			);
+		}
+		if let Some(hrmp) = &summary.hrmp {
+			log::info!(
+				"[{chain}] HRMP: {} messages, {} bytes per relay block, max queue depth {}",
+				hrmp.messages,
+				hrmp.bytes_per_block(),
+				hrmp.max_queue_depth
+			);
+		}
+	}
+	if summaries.len() > 1 {
+		log::info!(
+			"Aggregate TPS across {} chains: {}",
+			summaries.len(),
+			summaries.iter().map(|(_, summary)| summary.avg_tps()).sum::<f64>()
+		);
+	}
+}
+
+#[tokio::main]
+async fn main() -> Result<ExitCode, Box<dyn Error>> {
+	env_logger::init_from_env(
+		env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "info"),
+	);
+
+	let scenario = Args::parse().into_scenario()?;
+	log::info!("Resolved scenario:\n{}", scenario.to_toml()?);
+	let Scenario { topology, workload, measurement } = &scenario;
+
+	let xcm = workload.mode == BenchMode::XcmTransfer;
+	if measurement.para_finality &&
+		(!topology.attach.is_empty() || topology.paras.is_empty() || xcm)
+	{
+		return Err(
+			"The para-finality measurement needs spawned parachains, without xcm-transfer".into()
+		);
+	}
+	let hrmp = xcm && workload.xcm_direction == XcmDirection::ParaToPara;
+	if xcm && (!topology.attach.is_empty() || topology.paras.len() < 1 + hrmp as usize) {
+		return Err(if hrmp {
+			"The xcm-transfer mode needs two spawned parachains to transfer between them"
+		} else {
+			"The xcm-transfer mode needs a spawned relay chain and parachain"
+		}
+		.into());
+	}
+
+	let nthreads = if workload.threads == 0 {
+		std::thread::available_parallelism().unwrap_or(1usize.try_into().unwrap()).get()
+	} else {
+		workload.threads
+	};
+	let ntrans = nthreads * workload.count;
+
+	if workload.txs_per_account == 0 {
+		return Err("The number of transactions per account must be at least 1".into());
+	}
+	let modes = if workload.mode == BenchMode::Mix {
+		workload.mix()?.into_iter().map(|(mode, _)| mode).collect()
+	} else {
+		vec![workload.mode]
+	};
+	if workload.txs_per_account > 1 && modes.contains(&BenchMode::NftTransfer) {
+		return Err("Every sender only owns a single NFT to transfer".into());
+	}
+	workload.tip.validate()?;
+	if workload.remark_sizes.is_empty() {
+		return Err("At least one remark payload size must be given".into());
+	}
+	// The setup phases of the other modes cannot be repeated with the same accounts.
+	if workload.remark_sizes.len() > 1 && workload.mode != BenchMode::Remark {
+		return Err("Only the remark mode can sweep across payload sizes".into());
+	}
+	let nsenders = ntrans.div_ceil(workload.txs_per_account);
+
+	let naccs = if let Some(accounts) = workload.accounts {
+		assert!(
+			accounts >= nsenders,
+			"Number of accounts specified is less than the number of senders"
+		);
+		accounts
+	} else {
+		nsenders
+	};
+
+	let key_type = workload.key_type()?;
+	let mut send_accs = key_type.derive(naccs, SENDER_SEED);
+	// Fresh receivers are derived once the network is up, as they are not funded.
+	let fresh = workload.mode == BenchMode::FreshTransfer;
+	let mut recv_accs = if fresh { Vec::new() } else { key_type.derive(naccs, RECEIVER_SEED) };
+
+	let accs = send_accs
+		.iter()
+		.chain(recv_accs.iter())
+		.map(|p| (p.address(workload.ss58_prefix), FUNDS))
+		.collect::<Vec<_>>();
+
+	// A sufficient asset cannot be created by the senders themselves, so it is created in genesis,
+	// owned by the first sender, along with the balances of all of them.
+	let asset =
+		(workload.mode == BenchMode::AssetTransfer && workload.asset_sufficient).then(|| {
+			let owner = send_accs[0].address(workload.ss58_prefix);
+			let holders =
+				send_accs.iter().map(|p| p.address(workload.ss58_prefix)).collect::<Vec<_>>();
+			(owner, holders)
+		});
+
+	send_accs.truncate(nsenders);
+	recv_accs.truncate(nsenders);
+
+	let metrics = if let Some(url) = &measurement.prometheus_url {
+		Some(run_prometheus_endpoint(url, &measurement.prometheus_port).await?)
+	} else {
+		None
+	};
+
+	let attached = !topology.attach.is_empty();
+
+	// The network handle has to be kept alive for the whole benchmark, otherwise the nodes are torn
+	// down as soon as it is dropped.
+	let (_network, targets) = if attached {
+		log::info!("Attaching to {}", topology.attach.join(", "));
+		let target = Target {
+			chain: "attached".to_owned(),
+			urls: topology.attach.clone(),
+			delivery: None,
+			hrmp: None,
+			inclusion: None,
+		};
+		(None, vec![target])
+	} else {
+		let mut relay_genesis = genesis::relay_defaults();
+		if topology.paras.len() > 1 {
+			let para_ids = topology.paras.iter().map(|p| p.id).collect::<Vec<_>>();
+			genesis::open_hrmp_channels(&mut relay_genesis, &para_ids);
+		}
+		if let Some(path) = &topology.relay.genesis_override {
+			genesis::merge(&mut relay_genesis, genesis::load(path)?);
+		}
+
+		// In relay-only mode the transfers happen on the relay chain, and cross-chain transfers
+		// involve it, so the accounts are endowed there too, unless the custom chain spec already
+		// does it.
+		if topology.paras.is_empty() || xcm {
+			let funded = if let Some(chainspec) = &topology.relay.chainspec {
+				genesis::chainspec_funds(
+					chainspec,
+					send_accs.iter().chain(recv_accs.iter()).map(Keypair::account_id),
+				)?
+			} else {
+				false
+			};
+			if funded {
+				log::info!("Relay chain spec already funds the benchmark accounts");
+			} else {
+				genesis::fund(&mut relay_genesis, &accs);
+				if let Some((owner, holders)) = &asset {
+					genesis::fund_asset(&mut relay_genesis, workload.asset_id, owner, holders);
 				}
-				proc_mint += 1;
 			}
+		}
+
+		let mut para_genesis = Vec::new();
+		for para in &topology.paras {
+			let mut g = match &para.genesis_override {
+				Some(path) => genesis::load(path)?,
+				None => serde_json::json!({}),
+			};
+			genesis::fund(&mut g, &accs);
+			if let Some((owner, holders)) = &asset {
+				genesis::fund_asset(&mut g, workload.asset_id, owner, holders);
+			}
+			para_genesis.push(g);
+		}
 
-			let api2 = api.clone();
-
-			sender_lib::sign_txs::<_, _, PolkadotConfig>(
-				cll.into_iter().zip(recv_accs.into_iter()),
-				move |(coll, receiver)| {
-					let signer = PairSigner::new(coll.0);
-					let tx_params = DefaultExtrinsicParamsBuilder::new().nonce(2).build();
-					let tx_call = subxt::dynamic::tx(
-						"Nfts",
-						"transfer",
-						vec![
-							TxValue::primitive(coll.1.into()),
-							TxValue::primitive(0u32.into()),
-							TxValue::unnamed_variant(
-								"Id",
-								[TxValue::from_bytes(receiver.public())],
-							),
-						],
-					);
-
-					api2.tx()
-						.create_partial_offline(&tx_call, tx_params)
-						.expect("Failed to create partial offline transaction")
-						.sign(&signer)
-				},
+		let network = spawn_network(topology, relay_genesis, para_genesis).await?;
+
+		let mut nodes = Vec::new();
+		if topology.paras.is_empty() || xcm {
+			nodes.push(("relay".to_owned(), "validator01".to_owned()));
+		}
+		// Cross-chain transfers only involve the first parachain, or the first two when transferring
+		// between parachains.
+		let paras = if xcm { &topology.paras[..1 + hrmp as usize] } else { &topology.paras[..] };
+		nodes.extend(
+			paras
+				.iter()
+				.map(|p| (format!("para-{}", p.id), format!("{}01", collator_prefix(p.id)))),
+		);
+
+		let mut endpoints = Vec::new();
+		for (chain, name) in nodes {
+			let node = network.get_node(name)?;
+			wait_for_metric(
+				node,
+				"block_height{status=\"best\"}",
+				Duration::from_secs(300),
+				|bh| bh >= measurement.block_height as f64,
 			)
-		},
+			.await?;
+			endpoints.push((chain, node.ws_uri().to_owned()));
+		}
+
+		log::info!("Block height reached");
+
+		let targets = if xcm {
+			let relay = endpoints[0].clone();
+			let para = endpoints[1].clone();
+			let hrmp =
+				hrmp.then(|| (relay.1.clone(), (topology.paras[0].id, topology.paras[1].id)));
+			let (source, dest) = match workload.xcm_direction {
+				XcmDirection::RelayToPara => (relay, para),
+				XcmDirection::ParaToRelay => (para, relay),
+				XcmDirection::ParaToPara => (para, endpoints[2].clone()),
+			};
+			vec![Target {
+				chain: source.0,
+				urls: vec![source.1],
+				delivery: Some(dest),
+				hrmp,
+				inclusion: None,
+			}]
+		} else {
+			let relay = if measurement.para_finality {
+				Some(network.get_node("validator01")?.ws_uri().to_owned())
+			} else {
+				None
+			};
+			// With the para-finality measurement, the endpoints are those of the parachains.
+			endpoints
+				.into_iter()
+				.enumerate()
+				.map(|(i, (chain, url))| {
+					let inclusion = relay.clone().map(|relay| (relay, topology.paras[i].id));
+					Target { chain, urls: vec![url], delivery: None, hrmp: None, inclusion }
+				})
+				.collect()
+		};
+
+		(Some(network), targets)
 	};
 
-	log::info!("Transactions signed");
+	// Every transaction of the fresh-transfer mode creates its receiver. The derivation path is
+	// unique to the run, so that the receivers do not exist yet even on an attached network that
+	// was benchmarked before.
+	if fresh {
+		let run = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_millis();
+		log::info!("Deriving {ntrans} fresh receivers...");
+		recv_accs = key_type.derive(ntrans, &format!("{FRESH_SEED}//{run}"));
+	}
+
+	// All the chains are loaded simultaneously. Every parachain has its own state, so the same
+	// derived accounts are funded and used on each of them. The runs of a sweep follow each other
+	// on the same network.
+	let runs = workload.sweep();
+	let mut sweep = Vec::new();
+	let deadline = measurement.timeout.map(|timeout| Instant::now() + Duration::from_secs(timeout));
+	let mut timed_out = false;
+	for (i, run) in runs.iter().enumerate() {
+		if runs.len() > 1 {
+			log::info!(
+				"Run {} of {}: remark payload size {} bytes",
+				i + 1,
+				runs.len(),
+				run.remark_sizes[0]
+			);
+		}
+		let summaries = futures::future::try_join_all(targets.iter().map(|target| {
+			run_benchmark(
+				target.clone(),
+				ntrans,
+				attached || i > 0,
+				topology,
+				run,
+				send_accs.clone(),
+				recv_accs.clone(),
+				deadline,
+				metrics.clone(),
+			)
+		}))
+		.await?;
+		let summaries = targets
+			.iter()
+			.map(|target| target.chain.clone())
+			.zip(summaries)
+			.collect::<Vec<_>>();
+		report(&summaries);
+		timed_out = summaries.iter().any(|(_, summary)| summary.timed_out);
+		sweep.push((run.remark_sizes[0], summaries));
+		if timed_out {
+			break;
+		}
+	}
 
-	log::info!("Sending transactions...");
-	sender_lib::submit_txs(txs).await?;
-	log::info!("All sent");
+	if sweep.len() > 1 {
+		log::info!("Remark payload size sweep:");
+		for (size, summaries) in &sweep {
+			for (chain, summary) in summaries {
+				let (_, max_usage) = summary.length_usage();
+				log::info!(
+					"[{chain}] {size} bytes: TPS {}, {} bytes/s, {} bytes per block, max {:.1}% of \
+					 block length",
+					summary.avg_tps(),
+					summary.bytes_per_sec(),
+					summary.bytes_per_block(),
+					100.0 * max_usage
+				);
+			}
+		}
+	}
 
-	tokio::try_join!(subscriber)?;
-	log::debug!("Block subscriber joined");
+	if timed_out {
+		return Ok(ExitCode::from(TIMEOUT_EXIT_CODE));
+	}
 
-	while args.keep {
+	while measurement.keep {
 		tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
 	}
 
-	Ok(())
+	Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn counts(classes: &[usize], nclasses: usize) -> Vec<usize> {
+		(0..nclasses)
+			.map(|c| classes.iter().filter(|class| **class == c).count())
+			.collect()
+	}
+
+	fn tx(included: bool, dropped: Option<&str>, rejected: bool, invalid: bool) -> TxTimes {
+		let submitted = Instant::now();
+		TxTimes {
+			submitted,
+			validated: None,
+			broadcasted: None,
+			in_best_block: included.then_some(submitted),
+			in_finalized_block: None,
+			included: included.then_some(submitted),
+			dropped: dropped.map(str::to_owned),
+			dropped_at: None,
+			rejected,
+			invalid,
+			pending: false,
+		}
+	}
+
+	#[test]
+	fn account_txs_by_outcome() {
+		let pending = || TxTimes { pending: true, ..tx(false, None, false, false) };
+		// `(submitted, not submitted, included, dropped, invalid, unaccounted)` of every set of
+		// transactions, out of the number of transactions of the run.
+		let cases = [
+			(vec![], 0, (0, 0, 0, 0, 0, 0)),
+			(vec![tx(true, None, false, false)], 1, (1, 0, 1, 0, 0, 0)),
+			(vec![tx(false, None, false, false)], 1, (1, 0, 0, 0, 0, 1)),
+			(vec![tx(false, Some("Dropped"), false, false)], 1, (1, 0, 0, 1, 0, 0)),
+			(vec![tx(false, Some("Invalid"), false, true)], 1, (1, 0, 0, 0, 1, 0)),
+			// Rejected on submission, e.g. with an expired era.
+			(vec![tx(false, Some("Rejected"), true, true)], 1, (0, 0, 0, 0, 1, 0)),
+			// Included in a block which was retracted, then dropped.
+			(vec![tx(true, Some("Dropped"), false, false)], 1, (1, 0, 1, 0, 0, 0)),
+			// Still being submitted at the deadline.
+			(vec![pending()], 1, (0, 1, 0, 0, 0, 0)),
+			// Deadline reached during setup, before any transaction was sent.
+			(vec![], 5, (0, 5, 0, 0, 0, 0)),
+			(
+				vec![
+					tx(true, None, false, false),
+					tx(true, None, false, false),
+					tx(false, Some("Dropped"), false, false),
+					tx(false, Some("Rejected"), true, true),
+					tx(false, None, false, false),
+					pending(),
+				],
+				6,
+				(4, 1, 2, 1, 1, 1),
+			),
+		];
+		for (times, ntrans, expected) in cases {
+			let accounting = account_txs(&times, ntrans);
+			assert_eq!(
+				(
+					accounting.submitted,
+					accounting.not_submitted,
+					accounting.included,
+					accounting.dropped,
+					accounting.invalid,
+					accounting.unaccounted
+				),
+				expected,
+				"{times:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn mortal_outcomes_by_end_of_era() {
+		let dropped_at =
+			|at| TxTimes { dropped_at: Some(at), ..tx(false, Some("Dropped"), false, false) };
+		let times = [
+			tx(true, None, false, false),
+			// Dropped as the best chain reached the end of the era, or evicted before it.
+			dropped_at(20),
+			dropped_at(25),
+			dropped_at(12),
+			// Still in the pool at the end of the run.
+			tx(false, None, false, false),
+			TxTimes { pending: true, ..tx(false, None, false, false) },
+		];
+		assert_eq!(mortal_outcomes(&times, 20, 30), (3, 1));
+		assert_eq!(mortal_outcomes(&times, 20, 19), (2, 1));
+		assert_eq!(mortal_outcomes(&times, 26, 30), (1, 3));
+	}
+
+	#[test]
+	fn interleave_follows_uneven_weights() {
+		// Every full round of the weight sum assigns every class exactly its weight.
+		let classes = interleave(&[70, 20, 10], 200);
+		assert_eq!(counts(&classes, 3), [140, 40, 20]);
+		assert_eq!(counts(&classes[..100], 3), [70, 20, 10]);
+
+		let classes = interleave(&[3, 1], 10);
+		assert_eq!(classes, [0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+	}
+
+	#[test]
+	fn interleave_fewer_items_than_weight_sum() {
+		assert_eq!(interleave(&[5, 3, 2], 4), [0, 1, 2, 0]);
+		assert_eq!(counts(&interleave(&[5, 3, 2], 7), 3), [4, 2, 1]);
+		// A class with a tiny weight may get no item at all.
+		assert_eq!(interleave(&[1, 1000], 3), [1, 1, 1]);
+		assert!(interleave(&[1, 1], 0).is_empty());
+	}
 }
@@ -1,27 +1,42 @@
 use prometheus_endpoint::{
-	prometheus::{GaugeVec, IntGaugeVec, Opts},
+	prometheus::{exponential_buckets, GaugeVec, HistogramOpts, HistogramVec, IntGaugeVec, Opts},
 	Registry,
 };
-use std::net::ToSocketAddrs;
+use std::{net::ToSocketAddrs, time::Duration};
 
+#[derive(Clone)]
 pub struct StpsMetrics {
 	block_tps: GaugeVec,
 	block_tx_count: IntGaugeVec,
 	block_time: IntGaugeVec,
+	hrmp_queue_depth: IntGaugeVec,
+	tx_latency: HistogramVec,
 }
 
 impl StpsMetrics {
-	pub fn set(&self, tx_count: u64, block_time: u64, block_number: u32) {
+	pub fn set(&self, chain: &str, tx_count: u64, block_time: u64, block_number: u32) {
 		self.block_tps
-			.with_label_values(&[&block_number.to_string()])
+			.with_label_values(&[chain, &block_number.to_string()])
 			.set(tx_count as f64 / block_time as f64);
 		self.block_time
-			.with_label_values(&[&block_number.to_string()])
+			.with_label_values(&[chain, &block_number.to_string()])
 			.set(block_time as i64);
 		self.block_tx_count
-			.with_label_values(&[&block_number.to_string()])
+			.with_label_values(&[chain, &block_number.to_string()])
 			.set(tx_count as i64);
 	}
+
+	pub fn set_hrmp_queue(&self, chain: &str, depth: u64, block_number: u32) {
+		self.hrmp_queue_depth
+			.with_label_values(&[chain, &block_number.to_string()])
+			.set(depth as i64);
+	}
+
+	pub fn observe_latency(&self, chain: &str, stage: &str, latency: Duration) {
+		self.tx_latency
+			.with_label_values(&[chain, stage])
+			.observe(latency.as_secs_f64());
+	}
 }
 
 pub async fn run_prometheus_endpoint(
@@ -44,21 +59,39 @@ fn register_metrics(registry: &Registry) -> anyhow::Result<StpsMetrics> {
 		block_tps: prometheus_endpoint::register(
 			GaugeVec::new(
 				Opts::new("tps", "Transactions per second in the block"),
-				&["block_number"],
+				&["chain", "block_number"],
 			)?,
 			&registry,
 		)?,
 		block_tx_count: prometheus_endpoint::register(
 			IntGaugeVec::new(
 				Opts::new("tx_count", "Number of transactions in the block"),
-				&["block_number"],
+				&["chain", "block_number"],
 			)?,
 			&registry,
 		)?,
 		block_time: prometheus_endpoint::register(
 			IntGaugeVec::new(
 				Opts::new("block_time", "Block time delta in milliseconds"),
-				&["block_number"],
+				&["chain", "block_number"],
+			)?,
+			&registry,
+		)?,
+		hrmp_queue_depth: prometheus_endpoint::register(
+			IntGaugeVec::new(
+				Opts::new("hrmp_queue_depth", "Number of messages queued in the HRMP channel"),
+				&["chain", "block_number"],
+			)?,
+			&registry,
+		)?,
+		tx_latency: prometheus_endpoint::register(
+			HistogramVec::new(
+				HistogramOpts::new(
+					"tx_latency",
+					"Latency from the submission of transactions to a stage of their status, in seconds",
+				)
+				.buckets(exponential_buckets(0.05, 2.0, 12)?),
+				&["chain", "stage"],
 			)?,
 			&registry,
 		)?,
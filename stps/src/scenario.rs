@@ -0,0 +1,610 @@
+use clap::ValueEnum;
+use sender_lib::{
+	keypair::KeyType,
+	workload::{
+		self, AssetTransfer, BalanceTransfer, ContractTransfer, FreshTransfer, MultisigTransfer,
+		NftTransfer, ProxyTransfer, Remark, RemarkCall, XcmDirection, XcmTransfer, XcmTransferType,
+	},
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, path::Path, str::FromStr, sync::Arc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum BenchMode {
+	/// Standard balance transfers
+	#[value(alias = "transfer")]
+	#[serde(alias = "transfer")]
+	Stps,
+
+	/// NFT transfers
+	NftTransfer,
+
+	/// Balance transfers between Ethereum-style accounts, for Frontier/EVM-compatible chains
+	EthTransfer,
+
+	/// Fungible asset transfers
+	AssetTransfer,
+
+	/// Cross-chain transfers between the relay chain and the first parachain
+	XcmTransfer,
+
+	/// ERC20 contract transfers
+	ContractTransfer,
+
+	/// `System::remark` or `System::remark_with_event` calls
+	Remark,
+
+	/// Balance transfers of the existential deposit to fresh accounts, which are created by them
+	FreshTransfer,
+
+	/// Balance transfers from pure proxy accounts with `Proxy::proxy`
+	ProxyTransfer,
+
+	/// Balance transfers from 1-of-2 multisig accounts with `Multisig::as_multi_threshold_1`, which
+	/// dispatches them at once, without the approvals of the `as_multi` path
+	#[value(name = "multisig-threshold-1-transfer")]
+	#[serde(rename = "multisig-threshold-1-transfer")]
+	MultisigThreshold1Transfer,
+
+	/// Several of the other modes at once, as weighted by `--mix`
+	Mix,
+}
+
+impl BenchMode {
+	/// Default key type of the benchmark accounts.
+	pub fn key_type(&self) -> KeyType {
+		match self {
+			BenchMode::Stps |
+			BenchMode::NftTransfer |
+			BenchMode::AssetTransfer |
+			BenchMode::XcmTransfer |
+			BenchMode::ContractTransfer |
+			BenchMode::Remark |
+			BenchMode::FreshTransfer |
+			BenchMode::ProxyTransfer |
+			BenchMode::MultisigThreshold1Transfer |
+			BenchMode::Mix => KeyType::Sr25519,
+			BenchMode::EthTransfer => KeyType::Ethereum,
+		}
+	}
+
+	/// Name of the mode, as given on the command line.
+	pub fn name(&self) -> String {
+		self.to_possible_value().expect("No mode is skipped").get_name().to_owned()
+	}
+}
+
+/// Distribution of the tips paid by the senders of the measured transactions. It takes the same
+/// string form in scenario files as on the command line, e.g. `tip = "0-1000"`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TipDistribution {
+	/// Every sender pays the same tip.
+	Constant(u64),
+	/// The tips are spread evenly across the `[min, max]` range.
+	Uniform(u64, u64),
+	/// A `fraction` of the senders pays `tip`, the others pay nothing.
+	HighTippers { fraction: f64, tip: u64 },
+}
+
+impl Default for TipDistribution {
+	fn default() -> Self {
+		TipDistribution::Constant(0)
+	}
+}
+
+impl FromStr for TipDistribution {
+	type Err = String;
+
+	/// Parses `TIP` as a constant tip, `MIN-MAX` as a uniform range, and `FRACTION:TIP` as a
+	/// fraction of high tippers.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let int = |s: &str| s.trim().parse::<u64>().map_err(|e| format!("Invalid tip {s}: {e}"));
+		let tips = if let Some((min, max)) = s.split_once('-') {
+			TipDistribution::Uniform(int(min)?, int(max)?)
+		} else if let Some((fraction, tip)) = s.split_once(':') {
+			let fraction = fraction
+				.trim()
+				.parse()
+				.map_err(|e| format!("Invalid fraction {fraction}: {e}"))?;
+			TipDistribution::HighTippers { fraction, tip: int(tip)? }
+		} else {
+			TipDistribution::Constant(int(s)?)
+		};
+		tips.validate()?;
+		Ok(tips)
+	}
+}
+
+impl fmt::Display for TipDistribution {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TipDistribution::Constant(tip) => write!(f, "{tip}"),
+			TipDistribution::Uniform(min, max) => write!(f, "{min}-{max}"),
+			TipDistribution::HighTippers { fraction, tip } => write!(f, "{fraction}:{tip}"),
+		}
+	}
+}
+
+impl TryFrom<String> for TipDistribution {
+	type Error = String;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
+impl From<TipDistribution> for String {
+	fn from(tips: TipDistribution) -> Self {
+		tips.to_string()
+	}
+}
+
+impl TipDistribution {
+	pub fn validate(&self) -> Result<(), String> {
+		match *self {
+			TipDistribution::Uniform(min, max) if min > max =>
+				Err(format!("Empty tip range {min}-{max}")),
+			TipDistribution::HighTippers { fraction, .. } if !(0.0..=1.0).contains(&fraction) =>
+				Err(format!("The fraction of high tippers {fraction} is not between 0 and 1")),
+			_ => Ok(()),
+		}
+	}
+
+	/// Tips of `n` senders. Uniform tips follow a low-discrepancy sequence and high tippers are
+	/// spread evenly, so that the tips do not depend on the order in which the senders submit.
+	pub fn tips(&self, n: usize) -> Vec<u64> {
+		(0..n)
+			.map(|i| match *self {
+				TipDistribution::Constant(tip) => tip,
+				TipDistribution::Uniform(min, max) => {
+					let offset = (i as f64 * 0.618_033_988_749_895).fract();
+					min + ((offset * ((max - min) as f64 + 1.0)) as u64).min(max - min)
+				},
+				TipDistribution::HighTippers { fraction, tip } => {
+					let high = ((i + 1) as f64 * fraction).floor() > (i as f64 * fraction).floor();
+					if high {
+						tip
+					} else {
+						0
+					}
+				},
+			})
+			.collect()
+	}
+
+	/// Inclusive tip ranges over which inclusion latencies are reported: the distinct tips, or
+	/// quarters of a uniform range.
+	pub fn buckets(&self) -> Vec<(u64, u64)> {
+		match *self {
+			TipDistribution::Constant(tip) => vec![(tip, tip)],
+			TipDistribution::Uniform(min, max) => {
+				let width = (max - min).saturating_add(1).div_ceil(4);
+				(min..=max)
+					.step_by(width as usize)
+					.map(|lo| (lo, lo.saturating_add(width - 1).min(max)))
+					.collect()
+			},
+			TipDistribution::HighTippers { tip, .. } => vec![(0, 0), (tip, tip)],
+		}
+	}
+}
+
+/// A named workload of a benchmark run. Mixed runs have several classes, each sent by a share of
+/// the senders and counted separately.
+#[derive(Clone)]
+pub struct WorkloadClass {
+	pub name: String,
+	pub workload: Arc<dyn workload::Workload>,
+}
+
+/// Complete description of a benchmark run. It may be loaded from a TOML file with `--scenario`,
+/// and every command line flag overrides the corresponding value. The resolved scenario is printed
+/// at startup so that it can be saved and fed back to reproduce the run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+	pub topology: Topology,
+	pub workload: Workload,
+	pub measurement: Measurement,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Topology {
+	/// WebSocket URLs of already running nodes. If not empty, no network is spawned.
+	pub attach: Vec<String>,
+	pub relay: Relay,
+	/// Parachains to spawn. If not empty, the benchmark is performed on all of them simultaneously
+	/// instead of the relay chain.
+	pub paras: Vec<Para>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Relay {
+	/// Path to relay chain node binary.
+	pub bin: String,
+	/// Args for relay chain binary, in `name=value` or `name` form, without leading dashes.
+	pub args: Vec<String>,
+	/// Name of the relay chain spec.
+	pub chain: String,
+	/// Path to a custom relay chain spec.
+	pub chainspec: Option<String>,
+	/// Chainspec command template.
+	pub chainspec_command: Option<String>,
+	/// Path to a JSON genesis override, deep-merged over the default `HostConfiguration` override
+	/// and the generated balances.
+	pub genesis_override: Option<String>,
+	/// Number of validators.
+	pub nodes: usize,
+}
+
+impl Default for Relay {
+	fn default() -> Self {
+		Self {
+			bin: "polkadot".into(),
+			args: Vec::new(),
+			chain: "rococo-local".into(),
+			chainspec: None,
+			chainspec_command: None,
+			genesis_override: None,
+			nodes: 2,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Para {
+	/// Parachain id.
+	pub id: u32,
+	/// Path to parachain collator binary.
+	pub bin: String,
+	/// Args for parachain collator binary, in `name=value` or `name` form, without leading dashes.
+	pub args: Vec<String>,
+	/// Number of collators.
+	pub nodes: usize,
+	/// Name of the parachain spec. If not specified, defaults to the default chain spec of the
+	/// collator.
+	pub chain: Option<String>,
+	/// Path to a custom parachain spec.
+	pub chainspec: Option<String>,
+	/// Chainspec command template.
+	pub chainspec_command: Option<String>,
+	/// Path to a JSON genesis override, deep-merged with the generated balances.
+	pub genesis_override: Option<String>,
+}
+
+impl Default for Para {
+	fn default() -> Self {
+		Self {
+			id: 100,
+			bin: "polkadot-parachain".into(),
+			args: Vec::new(),
+			nodes: 1,
+			chain: None,
+			chainspec: None,
+			chainspec_command: None,
+			genesis_override: None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Workload {
+	/// Benchmark mode.
+	pub mode: BenchMode,
+	/// Number of threads to spawn for account deriving, transaction signing and transaction
+	/// sending. If set to 0, defaults to the available number of CPU cores.
+	pub threads: usize,
+	/// Number of transactions per thread.
+	pub count: usize,
+	/// Number of sender and receiver accounts to create, if different from
+	/// `threads * count / txs_per_account`.
+	pub accounts: Option<usize>,
+	/// Number of transactions signed by every sender, with consecutive nonces.
+	pub txs_per_account: usize,
+	/// Key type of the benchmark accounts, if not the default one of the mode.
+	pub key_type: Option<KeyType>,
+	/// Weighted modes of the `mix` mode, as a comma-separated list of `mode=weight`.
+	pub mix: Option<String>,
+	/// Tips paid by the senders of the measured transactions, as `TIP`, `MIN-MAX` or
+	/// `FRACTION:TIP`.
+	pub tip: TipDistribution,
+	/// Mortality of the measured transactions, in blocks. They are immortal if not set.
+	pub mortality: Option<u64>,
+	/// The ss58 prefix to use.
+	pub ss58_prefix: u16,
+	/// Asset id of the `asset-transfer` mode. With a non-sufficient asset, every sender creates its
+	/// own asset, with consecutive ids from an offset of this one which is unique to the run.
+	pub asset_id: u32,
+	/// Whether the asset of the `asset-transfer` mode is sufficient. A sufficient asset is created
+	/// in genesis and endowed to the senders, as only root can create one.
+	pub asset_sufficient: bool,
+	/// Transfer type of the `xcm-transfer` mode.
+	pub xcm_transfer: XcmTransferType,
+	/// Direction of the transfers of the `xcm-transfer` mode.
+	pub xcm_direction: XcmDirection,
+	/// Path to the Wasm code of the ERC20 contract of the `contract-transfer` mode, with the ABI of
+	/// the ink! ERC20 example.
+	pub contract_code: Option<String>,
+	/// Payload sizes of the `remark` mode, in bytes. With several sizes, the benchmark is run once
+	/// per size, in order.
+	pub remark_sizes: Vec<usize>,
+	/// Call of the `remark` mode.
+	pub remark_call: RemarkCall,
+}
+
+impl Default for Workload {
+	fn default() -> Self {
+		Self {
+			mode: BenchMode::Stps,
+			threads: 4,
+			count: 100,
+			accounts: None,
+			txs_per_account: 1,
+			key_type: None,
+			mix: None,
+			tip: TipDistribution::default(),
+			mortality: None,
+			ss58_prefix: 42,
+			asset_id: 1,
+			asset_sufficient: false,
+			xcm_transfer: XcmTransferType::Reserve,
+			xcm_direction: XcmDirection::RelayToPara,
+			contract_code: None,
+			remark_sizes: vec![32],
+			remark_call: RemarkCall::RemarkWithEvent,
+		}
+	}
+}
+
+impl Workload {
+	/// Modes of the `mix` mode with their weights. Modes involving other chains, other keys or
+	/// their own receivers cannot be mixed.
+	pub fn mix(&self) -> Result<Vec<(BenchMode, u32)>, Box<dyn Error>> {
+		let mix = self.mix.as_deref().ok_or("The mix mode needs --mix")?;
+		let mut modes = Vec::new();
+		for entry in mix.split(',') {
+			let (mode, weight) = entry
+				.split_once('=')
+				.ok_or_else(|| format!("Mix entry {entry} is not in the `mode=weight` form"))?;
+			let mode = BenchMode::from_str(mode.trim(), true)?;
+			let weight =
+				weight.trim().parse().map_err(|e| format!("Invalid weight of {mode:?}: {e}"))?;
+			if matches!(
+				mode,
+				BenchMode::EthTransfer |
+					BenchMode::XcmTransfer |
+					BenchMode::FreshTransfer |
+					BenchMode::Mix
+			) {
+				return Err(format!("The {} mode cannot be mixed", mode.name()).into());
+			}
+			modes.push((mode, weight));
+		}
+		if modes.iter().all(|(_, weight)| *weight == 0) {
+			return Err("At least one mixed mode must have a non-zero weight".into());
+		}
+		Ok(modes)
+	}
+
+	/// Key type of the benchmark accounts. Ethereum keys are required by the `eth-transfer` mode
+	/// and unusable by the other ones, which accept any key type with a `MultiSignature` variant.
+	pub fn key_type(&self) -> Result<KeyType, Box<dyn Error>> {
+		let default = self.mode.key_type();
+		match self.key_type {
+			Some(key_type) if (key_type == KeyType::Ethereum) != (default == KeyType::Ethereum) =>
+				Err(format!("The {} mode cannot use {key_type:?} keys", self.mode.name()).into()),
+			key_type => Ok(key_type.unwrap_or(default)),
+		}
+	}
+
+	/// Workloads of the successive runs of the benchmark, one per remark payload size.
+	pub fn sweep(&self) -> Vec<Workload> {
+		self.remark_sizes
+			.iter()
+			.map(|size| Workload { remark_sizes: vec![*size], ..self.clone() })
+			.collect()
+	}
+
+	/// Instantiates the workload classes of the benchmark mode with the configured settings, along
+	/// with their weights.
+	pub fn classes(
+		&self,
+		topology: &Topology,
+	) -> Result<Vec<(WorkloadClass, u32)>, Box<dyn Error>> {
+		let modes = if self.mode == BenchMode::Mix { self.mix()? } else { vec![(self.mode, 1)] };
+		let classes = modes
+			.into_iter()
+			.map(|(mode, weight)| {
+				let workload = self.build(mode, topology)?;
+				Ok((WorkloadClass { name: mode.name(), workload }, weight))
+			})
+			.collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+		// Setup events are attributed to the first class expecting them.
+		let mut events = Vec::new();
+		for (class, _) in &classes {
+			for phase in class.workload.setup_phases() {
+				if let Some((other, _)) = events.iter().find(|(_, event)| *event == phase.event) {
+					return Err(format!(
+						"The {other} and {} modes cannot be mixed, as their setup phases wait for \
+						 the same events",
+						class.name
+					)
+					.into());
+				}
+				events.push((&class.name, phase.event));
+			}
+		}
+		Ok(classes)
+	}
+
+	/// Instantiates the workload of the given mode with the configured settings.
+	fn build(
+		&self,
+		mode: BenchMode,
+		topology: &Topology,
+	) -> Result<Arc<dyn workload::Workload>, Box<dyn Error>> {
+		Ok(match mode {
+			BenchMode::Stps | BenchMode::EthTransfer => Arc::new(BalanceTransfer::default()),
+			BenchMode::NftTransfer => Arc::new(NftTransfer::default()),
+			BenchMode::AssetTransfer => {
+				let run = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+				let seed = run.as_millis().to_string();
+				Arc::new(AssetTransfer::new(self.asset_id, self.asset_sufficient, &seed))
+			},
+			BenchMode::XcmTransfer => {
+				// The destination parachain, which is the second one when transferring between
+				// parachains.
+				let dest = (self.xcm_direction == XcmDirection::ParaToPara) as usize;
+				let para_id = topology.paras.get(dest).map_or(Para::default().id, |p| p.id);
+				Arc::new(XcmTransfer::new(self.xcm_transfer, self.xcm_direction, para_id))
+			},
+			BenchMode::ContractTransfer => {
+				let path = self
+					.contract_code
+					.as_ref()
+					.ok_or("The contract-transfer mode needs the code of an ERC20 contract")?;
+				let code = std::fs::read(path)
+					.map_err(|e| format!("Cannot read contract code {path}: {e}"))?;
+				let run = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+				Arc::new(ContractTransfer::new(code, &run.as_millis().to_string()))
+			},
+			BenchMode::Remark => {
+				let size = *self.remark_sizes.first().ok_or("No remark payload size is given")?;
+				Arc::new(Remark::new(size, self.remark_call))
+			},
+			BenchMode::FreshTransfer => Arc::new(FreshTransfer::default()),
+			BenchMode::ProxyTransfer => Arc::new(ProxyTransfer::default()),
+			BenchMode::MultisigThreshold1Transfer => {
+				let run = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+				Arc::new(MultisigTransfer::new(&format!("//Custodian//{}", run.as_millis())))
+			},
+			BenchMode::Mix => unreachable!("Mixes are made of other modes"),
+		})
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Measurement {
+	/// Block height to wait for before starting the benchmark.
+	pub block_height: usize,
+	/// Keep the network running after the benchmark is finished until it's interrupted manually.
+	pub keep: bool,
+	/// Also measure the parachains through the inclusion of their candidates in the relay chain.
+	pub para_finality: bool,
+	/// Deadline of the benchmark, in seconds from the start of the first run.
+	pub timeout: Option<u64>,
+	/// Prometheus listener URL.
+	pub prometheus_url: Option<String>,
+	/// Prometheus listener port.
+	pub prometheus_port: u16,
+}
+
+impl Default for Measurement {
+	fn default() -> Self {
+		Self {
+			block_height: 5,
+			keep: false,
+			para_finality: false,
+			timeout: None,
+			prometheus_url: None,
+			prometheus_port: 65432,
+		}
+	}
+}
+
+impl Scenario {
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+		let path = path.as_ref();
+		let content = std::fs::read_to_string(path)
+			.map_err(|e| format!("Cannot read scenario {}: {e}", path.display()))?;
+		Ok(toml::from_str(&content)
+			.map_err(|e| format!("Cannot parse scenario {}: {e}", path.display()))?)
+	}
+
+	/// Renders the scenario back to TOML.
+	pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+		toml::to_string_pretty(self)
+	}
+}
+
+/// Splits a comma-separated `name=value,name` list as given on the command line into node args.
+pub fn split_args(args: &str) -> Vec<String> {
+	args.split(',').map(str::to_owned).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tip_distribution_string_forms() {
+		assert_eq!("100".parse(), Ok(TipDistribution::Constant(100)));
+		assert_eq!(" 100 ".parse(), Ok(TipDistribution::Constant(100)));
+		assert_eq!("0-1000".parse(), Ok(TipDistribution::Uniform(0, 1000)));
+		assert_eq!("7-7".parse(), Ok(TipDistribution::Uniform(7, 7)));
+		assert_eq!(
+			"0.25:500".parse(),
+			Ok(TipDistribution::HighTippers { fraction: 0.25, tip: 500 })
+		);
+		for tips in ["100", "0-1000", "0.25:500"] {
+			assert_eq!(tips.parse::<TipDistribution>().unwrap().to_string(), tips);
+		}
+	}
+
+	#[test]
+	fn tip_distribution_rejects_malformed_input() {
+		for tips in ["", "abc", "-5", "1-", "1-2-3", "10-5", "x:100", "0.5:", "1.5:100", "-0.5:100"]
+		{
+			assert!(tips.parse::<TipDistribution>().is_err(), "{tips} is accepted");
+		}
+	}
+
+	#[test]
+	fn tip_distribution_in_scenario_files() {
+		#[derive(Debug, PartialEq, Serialize, Deserialize)]
+		struct Tips {
+			tip: TipDistribution,
+		}
+		let tips: Tips = toml::from_str(r#"tip = "0.1:1000""#).unwrap();
+		assert_eq!(tips.tip, TipDistribution::HighTippers { fraction: 0.1, tip: 1000 });
+		assert_eq!(toml::to_string(&tips).unwrap().trim(), r#"tip = "0.1:1000""#);
+		assert!(toml::from_str::<Tips>(r#"tip = "10-5""#).is_err());
+	}
+
+	#[test]
+	fn tip_buckets_cover_the_range() {
+		assert_eq!(TipDistribution::Constant(10).buckets(), [(10, 10)]);
+		assert_eq!(
+			TipDistribution::HighTippers { fraction: 0.1, tip: 1000 }.buckets(),
+			[(0, 0), (1000, 1000)]
+		);
+		assert_eq!(
+			TipDistribution::Uniform(0, 999).buckets(),
+			[(0, 249), (250, 499), (500, 749), (750, 999)]
+		);
+		// The last quarter is cut short when the range does not split evenly.
+		assert_eq!(TipDistribution::Uniform(0, 10).buckets(), [(0, 2), (3, 5), (6, 8), (9, 10)]);
+		// Ranges narrower than four tips get a bucket per tip.
+		assert_eq!(TipDistribution::Uniform(5, 6).buckets(), [(5, 5), (6, 6)]);
+		assert_eq!(TipDistribution::Uniform(3, 3).buckets(), [(3, 3)]);
+		let quarter = 1 << 62;
+		assert_eq!(
+			TipDistribution::Uniform(0, u64::MAX).buckets(),
+			[
+				(0, quarter - 1),
+				(quarter, 2 * quarter - 1),
+				(2 * quarter, 3 * quarter - 1),
+				(3 * quarter, u64::MAX)
+			]
+		);
+	}
+}
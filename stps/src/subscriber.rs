@@ -0,0 +1,1179 @@
+use crate::{metrics::StpsMetrics, scenario::WorkloadClass};
+use parity_scale_codec::{Compact, Decode};
+use sender_lib::TxStage;
+use sp_core::hashing::blake2_256;
+use std::{
+	cmp::max,
+	collections::{BTreeMap, HashMap, HashSet},
+	time::{Duration, Instant},
+};
+use subxt::{
+	blocks::Block,
+	dynamic::Value,
+	error::DispatchError,
+	events::{EventDetails, Phase},
+	ext::scale_value::{At, ValueDef},
+	utils::H256,
+	OnlineClient, PolkadotConfig,
+};
+use tokio::sync::{mpsc::UnboundedSender, oneshot, watch};
+
+/// Finalized event of a workload setup phase, forwarded to the setup driver.
+pub struct SetupEvent {
+	/// Index of the workload class.
+	pub class: usize,
+	pub phase: usize,
+	pub fields: Vec<u8>,
+}
+
+/// TPS figures of a single chain collected by [`block_subscriber`].
+#[derive(Debug, Default, Clone)]
+pub struct TpsSummary {
+	/// Total number of counted transactions, i.e. the measured transactions dispatched
+	/// successfully.
+	pub ntrans: u64,
+	/// Name and number of counted transactions of every workload class.
+	pub classes: Vec<(String, u64)>,
+	/// Number of measured transactions whose dispatch failed.
+	pub failed: u64,
+	/// Dispatch errors of the failed transactions, with their number.
+	pub errors: Vec<(String, u64)>,
+	/// Sum of the block times of the blocks containing measured transactions, in milliseconds.
+	pub blocktime: u64,
+	/// Maximal number of counted transactions in a single block.
+	pub max_trans: u64,
+	/// Maximal TPS of a single block.
+	pub max_tps: f64,
+	/// Number of blocks containing counted transactions.
+	pub nblocks: u64,
+	/// Total `(ref_time, proof_size)` weight consumed by the counted transactions, if the workload
+	/// reports it.
+	pub weight: (u128, u128),
+	/// Total encoded length of the counted transactions, in bytes.
+	pub bytes: u64,
+	/// Sum of the lengths of all the extrinsics of the blocks containing counted transactions, in
+	/// bytes.
+	pub block_length: u64,
+	/// Maximal length of all the extrinsics of a single block, in bytes.
+	pub max_block_length: u64,
+	/// Length limit of the blocks for normal extrinsics, from the `System::BlockLength` constant.
+	pub length_limit: u64,
+	/// Weight consumed by every dispatch class, and by the whole blocks, in the blocks containing
+	/// counted transactions, from the `System::BlockWeight` storage.
+	pub block_weights: Vec<WeightUsage>,
+	/// `pallet::variant` and number of the events reported by the workload, emitted in the blocks
+	/// containing counted transactions.
+	pub events: Vec<(String, u64)>,
+	/// Sum of the timestamps of the blocks including the counted transactions, one per transaction,
+	/// in milliseconds.
+	pub included_at: u128,
+	/// Delivery figures of the messages sent by cross-chain workloads.
+	pub delivery: Option<DeliverySummary>,
+	/// Relay chain figures of the HRMP channel carrying the messages between parachains.
+	pub hrmp: Option<HrmpSummary>,
+	/// Inclusion latencies of the measured transactions by tip, if they pay tips.
+	pub tips: Vec<TipBucket>,
+	/// Latencies of the measured transactions from their submission to every stage of their
+	/// status stream.
+	pub stages: Vec<(TxStage, Latencies)>,
+	/// Number of mortal transactions which expired before their inclusion, as the best chain had
+	/// reached the end of their era when they were dropped, or by the end of the run.
+	pub expired: Option<u64>,
+	/// Number of mortal transactions dropped from the pool or found invalid before their inclusion
+	/// for another reason than the expiry of their era.
+	pub dropped: Option<u64>,
+	/// Best chain TPS and finality lag.
+	pub finality: Option<FinalitySummary>,
+	/// TPS of a parachain measured through the inclusion of its candidates in the relay chain.
+	pub inclusion: Option<InclusionSummary>,
+	/// Whether the deadline of the benchmark was reached before all the measured transactions were
+	/// accounted for.
+	pub timed_out: bool,
+	/// Outcomes of the measured transactions, as reported by their status streams.
+	pub accounting: TxAccounting,
+}
+
+/// Outcomes of the measured transactions, as reported by their status streams. Every transaction
+/// which was not included is either invalid, dropped, not submitted, or still unaccounted for.
+#[derive(Debug, Default, Clone)]
+pub struct TxAccounting {
+	/// Number of transactions accepted by the node on submission.
+	pub submitted: u64,
+	/// Number of transactions never sent, or still being submitted, when the deadline was reached.
+	pub not_submitted: u64,
+	/// Number of transactions included in a block.
+	pub included: u64,
+	/// Number of transactions dropped from the pool, e.g. as their era expired.
+	pub dropped: u64,
+	/// Number of transactions rejected on submission or found invalid by the pool.
+	pub invalid: u64,
+	/// Number of transactions neither included nor dropped, as still in the pool at the deadline.
+	pub unaccounted: u64,
+}
+
+impl TpsSummary {
+	/// Average TPS over the blocks containing counted transactions.
+	pub fn avg_tps(&self) -> f64 {
+		self.tps(self.ntrans)
+	}
+
+	/// Average TPS of the given number of the counted transactions, e.g. those of a workload class,
+	/// over the blocks containing counted transactions.
+	pub fn tps(&self, ntrans: u64) -> f64 {
+		if self.blocktime == 0 {
+			return 0.0;
+		}
+		ntrans as f64 / (self.blocktime as f64 / 1_000_f64)
+	}
+
+	/// Average `(ref_time, proof_size)` weight consumed by the counted transactions per block.
+	pub fn weight_per_block(&self) -> (f64, f64) {
+		let nblocks = self.nblocks.max(1) as f64;
+		(self.weight.0 as f64 / nblocks, self.weight.1 as f64 / nblocks)
+	}
+
+	/// Average number of bytes of counted transactions per second.
+	pub fn bytes_per_sec(&self) -> f64 {
+		self.tps(self.bytes)
+	}
+
+	/// Average number of bytes of counted transactions per block.
+	pub fn bytes_per_block(&self) -> f64 {
+		self.bytes as f64 / self.nblocks.max(1) as f64
+	}
+
+	/// Mean and maximal fractions of the length limit taken by the blocks containing counted
+	/// transactions.
+	pub fn length_usage(&self) -> (f64, f64) {
+		let limit = self.length_limit.max(1) as f64;
+		let mean = self.block_length as f64 / self.nblocks.max(1) as f64;
+		(mean / limit, self.max_block_length as f64 / limit)
+	}
+
+	/// Average number of the given number of reported events per block.
+	pub fn per_block(&self, nevents: u64) -> f64 {
+		nevents as f64 / self.nblocks.max(1) as f64
+	}
+
+	/// Mean timestamp at which the counted transactions were included, in milliseconds.
+	pub fn mean_included_at(&self) -> f64 {
+		self.included_at as f64 / self.ntrans.max(1) as f64
+	}
+}
+
+/// `(ref_time, proof_size)` weight consumed by a dispatch class, or by the whole blocks, in the
+/// blocks containing counted transactions, against its limit.
+#[derive(Debug, Default, Clone)]
+pub struct WeightUsage {
+	/// Dispatch class, or `total` for the whole blocks.
+	pub class: &'static str,
+	/// Limit of the weight consumed per block, from the `System::BlockWeights` constant: the
+	/// `max_total` of the dispatch class, or the `max_block` if it has none.
+	pub limit: (u128, u128),
+	/// Total weight consumed.
+	pub consumed: (u128, u128),
+	/// Maximal weight consumed in a single block, for each of the components.
+	pub max: (u128, u128),
+}
+
+impl WeightUsage {
+	fn add(&mut self, consumed: (u128, u128)) {
+		self.consumed.0 += consumed.0;
+		self.consumed.1 += consumed.1;
+		self.max.0 = max(self.max.0, consumed.0);
+		self.max.1 = max(self.max.1, consumed.1);
+	}
+
+	/// Mean fractions of the `(ref_time, proof_size)` limit consumed over the given number of
+	/// blocks.
+	pub fn mean_usage(&self, nblocks: u64) -> (f64, f64) {
+		let nblocks = nblocks.max(1) as f64;
+		(
+			self.consumed.0 as f64 / nblocks / self.limit.0.max(1) as f64,
+			self.consumed.1 as f64 / nblocks / self.limit.1.max(1) as f64,
+		)
+	}
+
+	/// Maximal fractions of the `(ref_time, proof_size)` limit consumed in a single block.
+	pub fn max_usage(&self) -> (f64, f64) {
+		(
+			self.max.0 as f64 / self.limit.0.max(1) as f64,
+			self.max.1 as f64 / self.limit.1.max(1) as f64,
+		)
+	}
+}
+
+/// Dispatch classes of the `System::BlockWeight` storage and the `System::BlockWeights` constant.
+const DISPATCH_CLASSES: [&str; 3] = ["normal", "operational", "mandatory"];
+
+/// `(ref_time, proof_size)` of a `Weight` value, if it is one.
+fn weight_parts(weight: Option<&Value<u32>>) -> Option<(u128, u128)> {
+	let weight = weight?;
+	Some((weight.at("ref_time")?.as_u128()?, weight.at("proof_size")?.as_u128()?))
+}
+
+/// Reads the limits of the weight consumed per block by every dispatch class, and by the whole
+/// blocks, from the `System::BlockWeights` constant.
+fn weight_limits(api: &OnlineClient<PolkadotConfig>) -> Result<Vec<WeightUsage>, subxt::Error> {
+	let invalid = || subxt::Error::Other("Invalid BlockWeights".into());
+	let addr = subxt::dynamic::constant("System", "BlockWeights");
+	let limits = api.constants().at(&addr)?.to_value()?;
+	let max_block = weight_parts(limits.at("max_block")).ok_or_else(invalid)?;
+	let mut usages = DISPATCH_CLASSES
+		.into_iter()
+		.map(|class| {
+			// `max_total` is an `Option`, whose `Some` variant holds the weight.
+			let max_total = limits.at("per_class").at(class).at("max_total").at(0);
+			let limit = weight_parts(max_total).unwrap_or(max_block);
+			WeightUsage { class, limit, ..Default::default() }
+		})
+		.collect::<Vec<_>>();
+	usages.push(WeightUsage { class: "total", limit: max_block, ..Default::default() });
+	Ok(usages)
+}
+
+/// Latencies of a set of transactions, sorted.
+#[derive(Debug, Default, Clone)]
+pub struct Latencies(Vec<Duration>);
+
+impl Latencies {
+	pub fn new(mut latencies: Vec<Duration>) -> Self {
+		latencies.sort();
+		Self(latencies)
+	}
+
+	/// Number of latencies.
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	pub fn mean(&self) -> Duration {
+		self.0.iter().sum::<Duration>() / self.0.len().max(1) as u32
+	}
+
+	/// Latency below which the given fraction of the transactions fall.
+	pub fn percentile(&self, p: f64) -> Duration {
+		let Some(last) = self.0.len().checked_sub(1) else {
+			return Duration::ZERO;
+		};
+		self.0[((last as f64 * p).round() as usize).min(last)]
+	}
+}
+
+/// Inclusion latencies of the measured transactions paying a tip in the given range, from their
+/// submission to their first inclusion in a block.
+#[derive(Debug, Default, Clone)]
+pub struct TipBucket {
+	/// Inclusive range of the tips.
+	pub tips: (u64, u64),
+	/// Number of transactions paying a tip in the range.
+	pub ntrans: u64,
+	/// Inclusion latencies of the included transactions.
+	pub latencies: Latencies,
+}
+
+/// Figures of the XCM messages processed by a destination chain, collected by
+/// [`message_subscriber`].
+#[derive(Debug, Default, Clone)]
+pub struct DeliverySummary {
+	/// Number of messages processed successfully.
+	pub processed: u64,
+	/// Number of messages whose processing failed.
+	pub failed: u64,
+	/// Sum of the block times of the blocks processing messages, in milliseconds.
+	pub blocktime: u64,
+	/// Sum of the timestamps of the blocks processing the messages, one per message, in
+	/// milliseconds.
+	pub processed_at: u128,
+}
+
+impl DeliverySummary {
+	/// Average number of messages processed per second over the blocks processing them.
+	pub fn avg_tps(&self) -> f64 {
+		if self.blocktime == 0 {
+			return 0.0;
+		}
+		(self.processed + self.failed) as f64 / (self.blocktime as f64 / 1_000_f64)
+	}
+
+	/// Mean timestamp at which the messages were processed, in milliseconds. Subtracting the mean
+	/// inclusion timestamp of the sent transactions gives the mean end-to-end delivery latency,
+	/// whichever order the messages are processed in.
+	pub fn mean_processed_at(&self) -> f64 {
+		self.processed_at as f64 / (self.processed + self.failed).max(1) as f64
+	}
+}
+
+/// Adds a dispatch error of a failed transaction to the summary.
+fn add_error(summary: &mut TpsSummary, error: String) {
+	match summary.errors.iter_mut().find(|(e, _)| *e == error) {
+		Some((_, n)) => *n += 1,
+		None => summary.errors.push((error, 1)),
+	}
+}
+
+/// Decodes the dispatch error of an `ExtrinsicFailed` event, naming the pallet and the variant of
+/// module errors.
+fn dispatch_error(api: &OnlineClient<PolkadotConfig>, ev: &EventDetails<PolkadotConfig>) -> String {
+	match DispatchError::decode_from(ev.field_bytes(), api.metadata()) {
+		Ok(DispatchError::Module(e)) => e.details_string(),
+		Ok(e) => e.to_string(),
+		Err(e) => format!("Undecodable dispatch error: {e}"),
+	}
+}
+
+/// Scrapes the finalized blocks until the `ntrans` measured transactions have been counted, except
+/// for the `lost` ones which are never going to be included, or until the deadline.
+#[allow(clippy::too_many_arguments)]
+pub async fn block_subscriber(
+	chain: String,
+	api: OnlineClient<PolkadotConfig>,
+	classes: Vec<WorkloadClass>,
+	ntrans: usize,
+	lost: watch::Receiver<u64>,
+	deadline: Option<Instant>,
+	setup_sender: Option<UnboundedSender<SetupEvent>>,
+	metrics: Option<StpsMetrics>,
+) -> Result<TpsSummary, subxt::Error> {
+	let setup_phases = classes.iter().map(|c| c.workload.setup_phases()).collect::<Vec<_>>();
+	let counted_calls = classes.iter().map(|c| c.workload.counted_call()).collect::<Vec<_>>();
+	// Classes counting the events of their extrinsics rather than the extrinsics themselves. Their
+	// extrinsics only count if dispatched successfully and emitting the event.
+	let counted_events = classes
+		.iter()
+		.map(|c| c.workload.counted_event().filter(|_| c.workload.counts_events()))
+		.collect::<Vec<_>>();
+	let reports_weight = classes.iter().any(|c| c.workload.reports_weight());
+	let mut reported_events = Vec::new();
+	for event in classes.iter().flat_map(|c| c.workload.reported_events()) {
+		if !reported_events.contains(&event) {
+			reported_events.push(event);
+		}
+	}
+	// Extrinsics of every dispatch class count toward the limit of normal ones, which is the lowest.
+	let addr = subxt::dynamic::constant("System", "BlockLength");
+	let limit = api.constants().at(&addr)?.to_value()?;
+	let length_limit = limit.at("max").at("normal").and_then(|l| l.as_u128());
+	let length_limit =
+		length_limit.ok_or_else(|| subxt::Error::Other("Invalid BlockLength".into()))? as u64;
+	let block_weights = weight_limits(&api)?;
+	let mut blocks_sub = api.blocks().subscribe_finalized().await?;
+
+	let mut last_block_timestamp = 0;
+	let mut summary = TpsSummary {
+		classes: classes.iter().map(|c| (c.name.clone(), 0)).collect(),
+		length_limit,
+		block_weights,
+		events: reported_events.iter().map(|(p, v)| (format!("{p}::{v}"), 0)).collect(),
+		..Default::default()
+	};
+	let mut _first_tran_timestamp = 0;
+	log::debug!("[{chain}] Starting chain watcher");
+	loop {
+		let next = match deadline {
+			Some(deadline) => tokio::time::timeout_at(deadline.into(), blocks_sub.next()).await,
+			None => Ok(blocks_sub.next().await),
+		};
+		let Ok(next) = next else {
+			log::warn!("[{chain}] Deadline reached with {} transactions counted", summary.ntrans);
+			summary.timed_out = true;
+			break;
+		};
+		let Some(block) = next else {
+			break;
+		};
+		let block = block?;
+		let mut last_block_ntrans = 0;
+		let mut last_block_failed = 0;
+		let mut last_block_classes = vec![0; classes.len()];
+		let mut last_blocktime: u64 = 0;
+		let mut last_block_bytes = 0;
+		let mut last_block_length = 0;
+		// Index of every measured extrinsic, along with its class. They are counted once their
+		// dispatch outcome is known from the events.
+		let mut counted = HashMap::new();
+		let mut emitted = HashSet::new();
+
+		for ex in block.extrinsics().await?.iter() {
+			let len = ex.bytes().len() as u64;
+			last_block_length += len;
+			match (ex.pallet_name()?, ex.variant_name()?) {
+				("Timestamp", "set") => {
+					let timestamp: Compact<u64> = Decode::decode(&mut &ex.field_bytes()[..])?;
+					let timestamp = u64::from(timestamp);
+					last_blocktime = timestamp - last_block_timestamp;
+					if summary.ntrans == 0 {
+						_first_tran_timestamp = timestamp;
+					}
+					last_block_timestamp = timestamp;
+				},
+				call =>
+					if let Some(class) = counted_calls.iter().position(|c| *c == call) {
+						last_block_bytes += len;
+						counted.insert(ex.index(), class);
+					},
+			}
+		}
+
+		let mut proc_setup = setup_phases.iter().map(|p| vec![0; p.len()]).collect::<Vec<_>>();
+		let mut last_block_weight = (0, 0);
+		let mut last_block_events = vec![0; reported_events.len()];
+		let has_setup = setup_phases.iter().any(|p| !p.is_empty());
+		if has_setup || !counted.is_empty() {
+			for ev in block.events().await?.iter() {
+				let ev = ev?;
+				// log::info!("EVENT {}::{}", ev.pallet_name(), ev.variant_name());
+				let event = (ev.pallet_name(), ev.variant_name());
+				let index = match ev.phase() {
+					Phase::ApplyExtrinsic(i) => Some(i),
+					_ => None,
+				};
+				let class = index.and_then(|i| counted.get(&i).copied());
+				// The dispatch outcome is the last event of every extrinsic.
+				if let (Some(i), Some(class)) = (index, class) {
+					match event {
+						("System", "ExtrinsicSuccess") =>
+							if counted_events[class].is_none() || emitted.contains(&i) {
+								last_block_ntrans += 1;
+								last_block_classes[class] += 1;
+							} else {
+								let (pallet, call) = counted_calls[class];
+								last_block_failed += 1;
+								add_error(
+									&mut summary,
+									format!("{pallet}::{call} inner call failed"),
+								);
+							},
+						("System", "ExtrinsicFailed") => {
+							last_block_failed += 1;
+							add_error(&mut summary, dispatch_error(&api, &ev));
+						},
+						event if counted_events[class] == Some(event) => {
+							emitted.insert(i);
+						},
+						_ => (),
+					}
+				}
+				if reports_weight && event == ("System", "ExtrinsicSuccess") && class.is_some() {
+					let fields = ev.field_values()?;
+					let weight = fields.at("dispatch_info").at("weight");
+					let part =
+						|name: &str| weight.at(name).and_then(|w| w.as_u128()).unwrap_or_default();
+					last_block_weight.0 += part("ref_time");
+					last_block_weight.1 += part("proof_size");
+				}
+				if let Some(i) = reported_events.iter().position(|e| *e == event) {
+					last_block_events[i] += 1;
+				}
+				let setup = setup_phases.iter().enumerate().find_map(|(class, phases)| {
+					phases.iter().position(|p| p.event == event).map(|phase| (class, phase))
+				});
+				if let Some((class, phase)) = setup {
+					proc_setup[class][phase] += 1;
+					if let Some(ref sender) = setup_sender {
+						let fields = ev.field_bytes().to_vec();
+						// The setup driver is gone if the benchmark failed, so there is nothing
+						// left to measure.
+						if sender.send(SetupEvent { class, phase, fields }).is_err() {
+							log::debug!("[{chain}] Setup driver exited, stopping chain watcher");
+							return Ok(summary);
+						}
+					}
+				}
+			}
+		}
+
+		if last_block_ntrans + last_block_failed > 0 {
+			log::debug!(
+				"[{chain}] Last block time {last_blocktime}, {last_block_ntrans} transactions in block"
+			);
+			summary.blocktime += last_blocktime;
+			summary.ntrans += last_block_ntrans;
+			summary.failed += last_block_failed;
+			for (class, n) in summary.classes.iter_mut().zip(&last_block_classes) {
+				class.1 += n;
+			}
+			summary.included_at += last_block_timestamp as u128 * last_block_ntrans as u128;
+			summary.nblocks += 1;
+			summary.weight.0 += last_block_weight.0;
+			summary.weight.1 += last_block_weight.1;
+			summary.bytes += last_block_bytes;
+			summary.block_length += last_block_length;
+			summary.max_block_length = max(summary.max_block_length, last_block_length);
+			for (event, n) in summary.events.iter_mut().zip(&last_block_events) {
+				event.1 += n;
+			}
+			summary.max_trans = max(summary.max_trans, last_block_ntrans);
+			let block_tps = last_block_ntrans as f64 / (last_blocktime as f64 / 1_000_f64);
+			summary.max_tps = f64::max(summary.max_tps, block_tps);
+			log::info!("[{chain}] TPS in block: {:?}", block_tps);
+			log::info!("[{chain}] TPS average: {}", summary.avg_tps());
+			if classes.len() > 1 {
+				for ((name, total), n) in summary.classes.iter().zip(&last_block_classes) {
+					log::info!(
+						"[{chain}] {name}: {n} transactions in block, TPS average: {}",
+						summary.tps(*total)
+					);
+				}
+			}
+			log::info!(
+				"[{chain}] Max TPS: {}, max transactions per block {}",
+				summary.max_tps,
+				summary.max_trans
+			);
+			if last_block_failed > 0 {
+				log::info!(
+					"[{chain}] Failed transactions in block: {last_block_failed}, {} in total",
+					summary.failed
+				);
+			}
+			if reports_weight {
+				log::info!(
+					"[{chain}] Weight consumed in block: ref_time {}, proof_size {}",
+					last_block_weight.0,
+					last_block_weight.1
+				);
+			}
+			for ((name, total), n) in summary.events.iter().zip(&last_block_events) {
+				log::info!(
+					"[{chain}] {name}: {n} events in block, {} per block on average",
+					summary.per_block(*total)
+				);
+			}
+			log::info!(
+				"[{chain}] Transaction bytes in block: {last_block_bytes}, block length \
+				 {last_block_length} of {length_limit} ({:.1}%)",
+				100.0 * last_block_length as f64 / length_limit as f64
+			);
+			// The state of the block holds the weight consumed by all its extrinsics and hooks,
+			// until the next block resets it.
+			let addr = subxt::dynamic::storage("System", "BlockWeight", ());
+			if let Some(consumed) = block.storage().fetch(&addr).await? {
+				let consumed = consumed.to_value()?;
+				let mut total = (0, 0);
+				for (usage, class) in summary.block_weights.iter_mut().zip(DISPATCH_CLASSES) {
+					let weight = weight_parts(consumed.at(class)).unwrap_or_default();
+					usage.add(weight);
+					total = (total.0 + weight.0, total.1 + weight.1);
+				}
+				if let Some(usage) = summary.block_weights.last_mut() {
+					usage.add(total);
+					log::info!(
+						"[{chain}] Block weight: ref_time {} ({:.1}%), proof_size {} ({:.1}%)",
+						total.0,
+						100.0 * total.0 as f64 / usage.limit.0.max(1) as f64,
+						total.1,
+						100.0 * total.1 as f64 / usage.limit.1.max(1) as f64
+					);
+				}
+			}
+			if let Some(ref metrics) = metrics {
+				metrics.set(&chain, last_block_ntrans, last_blocktime, block.number());
+			}
+		}
+
+		for (phases, proc) in setup_phases.iter().zip(proc_setup) {
+			for (phase, proc) in phases.iter().zip(proc) {
+				if proc > 0 {
+					log::info!("[{chain}] {} in block: {proc}", phase.name);
+				}
+			}
+		}
+
+		log::info!("[{chain}] Total transactions processed: {}", summary.ntrans);
+
+		// Transactions dropped from the pool are known once the submission is over.
+		if summary.ntrans + summary.failed + *lost.borrow() >= ntrans as u64 {
+			break;
+		}
+	}
+	Ok(summary)
+}
+
+/// Scrapes the finalized blocks of the destination chain of a cross-chain workload until `nmsgs`
+/// messages have been processed, successfully or not, according to the `MessageQueue` events. No
+/// other messages are expected on a freshly spawned network, so all of them are counted. The number
+/// of messages is only known once the sending transactions dispatched successfully have been
+/// counted, as the others send none. The number of messages processed so far is published to
+/// `delivered`.
+pub async fn message_subscriber(
+	chain: String,
+	api: OnlineClient<PolkadotConfig>,
+	nmsgs: watch::Receiver<Option<u64>>,
+	delivered: watch::Sender<u64>,
+) -> Result<DeliverySummary, subxt::Error> {
+	let mut blocks_sub = api.blocks().subscribe_finalized().await?;
+
+	let mut last_block_timestamp = 0;
+	let mut summary = DeliverySummary::default();
+	log::debug!("[{chain}] Starting message watcher");
+	while let Some(block) = blocks_sub.next().await {
+		let block = block?;
+		let mut last_blocktime: u64 = 0;
+
+		for ex in block.extrinsics().await?.iter() {
+			if (ex.pallet_name()?, ex.variant_name()?) == ("Timestamp", "set") {
+				let timestamp: Compact<u64> = Decode::decode(&mut &ex.field_bytes()[..])?;
+				let timestamp = u64::from(timestamp);
+				last_blocktime = timestamp - last_block_timestamp;
+				last_block_timestamp = timestamp;
+			}
+		}
+
+		let (mut processed, mut failed) = (0, 0);
+		for ev in block.events().await?.iter() {
+			let ev = ev?;
+			match (ev.pallet_name(), ev.variant_name()) {
+				("MessageQueue", "Processed") => {
+					let fields = ev.field_values()?;
+					if fields.at("success").and_then(|s| s.as_bool()).unwrap_or_default() {
+						processed += 1;
+					} else {
+						failed += 1;
+					}
+				},
+				("MessageQueue", "ProcessingFailed") => failed += 1,
+				_ => (),
+			}
+		}
+
+		if processed + failed > 0 {
+			summary.blocktime += last_blocktime;
+			summary.processed += processed;
+			summary.failed += failed;
+			summary.processed_at += last_block_timestamp as u128 * (processed + failed) as u128;
+			log::info!("[{chain}] Messages in block: {processed} processed, {failed} failed");
+			log::info!("[{chain}] Received TPS average: {}", summary.avg_tps());
+			let _ = delivered.send(summary.processed + summary.failed);
+		}
+
+		let nmsgs = *nmsgs.borrow();
+		if nmsgs.is_some_and(|n| summary.processed + summary.failed >= n) {
+			break;
+		}
+	}
+	Ok(summary)
+}
+
+/// Relay chain figures of an HRMP channel collected by [`hrmp_sampler`].
+#[derive(Debug, Default, Clone)]
+pub struct HrmpSummary {
+	/// Number of messages sent over the channel. Several XCMs may be concatenated into one of them.
+	pub messages: u64,
+	/// Total size of the messages sent over the channel, in bytes.
+	pub bytes: u64,
+	/// Number of relay chain blocks in which messages were sent.
+	pub blocks: u64,
+	/// Maximal number of messages queued in the channel at once.
+	pub max_queue_depth: u64,
+}
+
+impl HrmpSummary {
+	/// Average size of the messages sent per relay chain block, over the blocks sending them.
+	pub fn bytes_per_block(&self) -> f64 {
+		self.bytes as f64 / self.blocks.max(1) as f64
+	}
+}
+
+/// Samples the HRMP channel from the `sender` to the `recipient` parachain at every finalized relay
+/// chain block, until the `nmsgs` XCMs have been delivered, as counted by [`message_subscriber`]
+/// into `delivered`, and the queue has been drained. The XCMs are not counted on the channel, as
+/// several of them may be concatenated into one HRMP message.
+pub async fn hrmp_sampler(
+	chain: String,
+	api: OnlineClient<PolkadotConfig>,
+	(sender, recipient): (u32, u32),
+	nmsgs: watch::Receiver<Option<u64>>,
+	delivered: watch::Receiver<u64>,
+	metrics: Option<StpsMetrics>,
+) -> Result<HrmpSummary, subxt::Error> {
+	let channel_id = || {
+		vec![Value::named_composite([
+			("sender", Value::unnamed_composite([Value::u128(sender.into())])),
+			("recipient", Value::unnamed_composite([Value::u128(recipient.into())])),
+		])]
+	};
+	let mut blocks_sub = api.blocks().subscribe_finalized().await?;
+
+	let mut summary = HrmpSummary::default();
+	log::debug!("[{chain}] Starting HRMP channel {sender} -> {recipient} watcher");
+	while let Some(block) = blocks_sub.next().await {
+		let block = block?;
+		let storage = block.storage();
+
+		let channel = subxt::dynamic::storage("Hrmp", "HrmpChannels", channel_id());
+		let Some(channel) = storage.fetch(&channel).await? else {
+			log::warn!("[{chain}] HRMP channel {sender} -> {recipient} is not open");
+			continue;
+		};
+		let channel = channel.to_value()?;
+		let queue_depth = channel.at("msg_count").and_then(|c| c.as_u128()).unwrap_or_default();
+		let queue_size = channel.at("total_size").and_then(|s| s.as_u128()).unwrap_or_default();
+
+		// Messages stay in the channel until the recipient processes them, which cannot happen in
+		// the block they are sent in, so the ones sent in this block are all there.
+		let contents = subxt::dynamic::storage("Hrmp", "HrmpChannelContents", channel_id());
+		let (mut messages, mut bytes) = (0, 0);
+		if let Some(contents) = storage.fetch(&contents).await? {
+			let contents = contents.to_value()?;
+			if let ValueDef::Composite(contents) = &contents.value {
+				for message in contents.values() {
+					let sent_at = message.at("sent_at").and_then(|s| s.as_u128());
+					if sent_at == Some(block.number().into()) {
+						messages += 1;
+						bytes += match message.at("data").map(|d| &d.value) {
+							Some(ValueDef::Composite(data)) => data.len() as u64,
+							_ => 0,
+						};
+					}
+				}
+			}
+		}
+
+		summary.max_queue_depth = max(summary.max_queue_depth, queue_depth as u64);
+		if messages > 0 {
+			summary.messages += messages;
+			summary.bytes += bytes;
+			summary.blocks += 1;
+		}
+		log::info!(
+			"[{chain}] HRMP queue depth: {queue_depth} messages, {queue_size} bytes; {messages} messages, {bytes} bytes sent in block"
+		);
+		if let Some(ref metrics) = metrics {
+			metrics.set_hrmp_queue(&chain, queue_depth as u64, block.number());
+		}
+
+		if hrmp_drained(*nmsgs.borrow(), *delivered.borrow(), queue_depth) {
+			break;
+		}
+	}
+	Ok(summary)
+}
+
+/// Whether all the `nmsgs` XCMs sent over an HRMP channel have been delivered and its queue of
+/// `queue_depth` messages has been drained.
+fn hrmp_drained(nmsgs: Option<u64>, delivered: u64, queue_depth: u128) -> bool {
+	nmsgs.is_some_and(|n| delivered >= n) && queue_depth == 0
+}
+
+/// Best chain and finality figures collected by [`finality_tracker`]. The transactions are counted
+/// whatever their dispatch outcome, so that both chains are measured alike.
+#[derive(Debug, Default, Clone)]
+pub struct FinalitySummary {
+	/// Number of measured transactions included in the blocks of the best chain.
+	pub best_ntrans: u64,
+	/// Sum of the block times of the best chain blocks containing measured transactions, in
+	/// milliseconds.
+	pub best_blocktime: u64,
+	/// Number of measured transactions included in the finalized blocks.
+	pub finalized_ntrans: u64,
+	/// Sum of the block times of the finalized blocks containing measured transactions, in
+	/// milliseconds.
+	pub finalized_blocktime: u64,
+	/// Number of finalized blocks.
+	pub nfinalized: u64,
+	/// Sum and maximum of the number of best blocks ahead of every block when it was finalized.
+	pub lag_blocks: (u64, u64),
+	/// Number of finalized blocks which were seen as best blocks first.
+	pub ntimed: u64,
+	/// Sum and maximum of the time from the import of every block as a best block to its
+	/// finalization, over the blocks seen as best blocks first.
+	pub lag_time: (Duration, Duration),
+}
+
+impl FinalitySummary {
+	/// Average TPS over the best chain blocks containing measured transactions.
+	pub fn best_tps(&self) -> f64 {
+		if self.best_blocktime == 0 {
+			return 0.0;
+		}
+		self.best_ntrans as f64 / (self.best_blocktime as f64 / 1_000_f64)
+	}
+
+	/// Average TPS over the finalized blocks containing measured transactions.
+	pub fn finalized_tps(&self) -> f64 {
+		if self.finalized_blocktime == 0 {
+			return 0.0;
+		}
+		self.finalized_ntrans as f64 / (self.finalized_blocktime as f64 / 1_000_f64)
+	}
+
+	/// Mean number of best blocks ahead of the blocks when they were finalized.
+	pub fn mean_lag_blocks(&self) -> f64 {
+		self.lag_blocks.0 as f64 / self.nfinalized.max(1) as f64
+	}
+
+	/// Mean time from the import of the blocks as best blocks to their finalization.
+	pub fn mean_lag_time(&self) -> Duration {
+		self.lag_time.0 / self.ntimed.max(1) as u32
+	}
+}
+
+/// Measured transactions and time of a block, as seen by [`finality_tracker`].
+#[derive(Debug, Clone, Copy)]
+struct BlockRecord {
+	ntrans: u64,
+	timestamp: u64,
+	blocktime: u64,
+	/// When the block was first seen as a best block.
+	seen_best: Option<Instant>,
+}
+
+/// Returns the record of a block, counting its measured transactions the first time it is seen.
+async fn block_record(
+	api: &OnlineClient<PolkadotConfig>,
+	counted_calls: &[(&'static str, &'static str)],
+	records: &mut HashMap<H256, BlockRecord>,
+	block: &Block<PolkadotConfig, OnlineClient<PolkadotConfig>>,
+) -> Result<BlockRecord, subxt::Error> {
+	if let Some(record) = records.get(&block.hash()) {
+		return Ok(*record);
+	}
+	let mut ntrans = 0;
+	let mut timestamp = 0;
+	for ex in block.extrinsics().await?.iter() {
+		match (ex.pallet_name()?, ex.variant_name()?) {
+			("Timestamp", "set") => {
+				let now: Compact<u64> = Decode::decode(&mut &ex.field_bytes()[..])?;
+				timestamp = now.into();
+			},
+			call =>
+				if counted_calls.contains(&call) {
+					ntrans += 1;
+				},
+		}
+	}
+	let parent = block.header().parent_hash;
+	let parent_timestamp = match records.get(&parent) {
+		Some(record) => record.timestamp,
+		None => {
+			let now = subxt::dynamic::storage("Timestamp", "Now", ());
+			let now = api.storage().at(parent).fetch_or_default(&now).await?;
+			now.to_value()?.as_u128().unwrap_or_default() as u64
+		},
+	};
+	let blocktime = timestamp.saturating_sub(parent_timestamp);
+	let record = BlockRecord { ntrans, timestamp, blocktime, seen_best: None };
+	records.insert(block.hash(), record);
+	Ok(record)
+}
+
+/// Follows the best and the finalized blocks side by side until `stop` fires and the block
+/// finalized by then has been processed, counting the measured transactions included in both
+/// chains and sampling the finality lag at every finalized block. The number of every best block
+/// is published to `best`.
+pub async fn finality_tracker(
+	chain: String,
+	api: OnlineClient<PolkadotConfig>,
+	classes: Vec<WorkloadClass>,
+	best: watch::Sender<u64>,
+	mut stop: oneshot::Receiver<()>,
+) -> Result<FinalitySummary, subxt::Error> {
+	let counted_calls = classes.iter().map(|c| c.workload.counted_call()).collect::<Vec<_>>();
+	let mut best_sub = api.blocks().subscribe_best().await?;
+	let mut finalized_sub = api.blocks().subscribe_finalized().await?;
+
+	let mut records = HashMap::new();
+	// Hash of the best chain block at every height, replaced on reorgs.
+	let mut best_chain = BTreeMap::new();
+	let mut best_number = 0;
+	let mut finalized_number = 0;
+	let mut stop_at = None;
+	let mut summary = FinalitySummary::default();
+	log::debug!("[{chain}] Starting finality tracker");
+	loop {
+		tokio::select! {
+			_ = &mut stop, if stop_at.is_none() => {
+				let finalized: u64 = api.blocks().at_latest().await?.number().into();
+				if finalized_number >= finalized {
+					break;
+				}
+				stop_at = Some(finalized);
+			},
+			block = best_sub.next() => {
+				let Some(block) = block else { break };
+				let mut block = block?;
+				let record = block_record(&api, &counted_calls, &mut records, &block).await?;
+				if record.seen_best.is_none() {
+					records.entry(block.hash()).and_modify(|r| r.seen_best = Some(Instant::now()));
+				}
+				best_number = block.number().into();
+				let _ = best.send(best_number);
+				// The blocks above the new best block are no longer in the best chain, and nor
+				// are the ones below it, up to the common ancestor, if the new best block is on
+				// another fork.
+				best_chain.split_off(&(best_number + 1));
+				let mut number = best_number;
+				loop {
+					best_chain.insert(number, block.hash());
+					let parent = block.header().parent_hash;
+					match best_chain.get(&(number.saturating_sub(1))) {
+						Some(hash) if number > 0 && *hash != parent => {
+							block = api.blocks().at(parent).await?;
+							block_record(&api, &counted_calls, &mut records, &block).await?;
+							number -= 1;
+						},
+						_ => break,
+					}
+				}
+			},
+			block = finalized_sub.next() => {
+				let Some(block) = block else { break };
+				let block = block?;
+				let record = block_record(&api, &counted_calls, &mut records, &block).await?;
+				if record.ntrans > 0 {
+					summary.finalized_ntrans += record.ntrans;
+					summary.finalized_blocktime += record.blocktime;
+				}
+				let number: u64 = block.number().into();
+				let lag = best_number.saturating_sub(number);
+				summary.nfinalized += 1;
+				summary.lag_blocks.0 += lag;
+				summary.lag_blocks.1 = max(summary.lag_blocks.1, lag);
+				let lag_time = record.seen_best.map(|seen| seen.elapsed());
+				if let Some(lag_time) = lag_time {
+					summary.ntimed += 1;
+					summary.lag_time.0 += lag_time;
+					summary.lag_time.1 = max(summary.lag_time.1, lag_time);
+				}
+				log::debug!(
+					"[{chain}] Block {number} finalized {lag} blocks behind the best block, after \
+					 {lag_time:?}"
+				);
+				finalized_number = number;
+				if stop_at.is_some_and(|n| number >= n) {
+					break;
+				}
+			},
+		}
+	}
+
+	for hash in best_chain.values() {
+		let record = &records[hash];
+		if record.ntrans > 0 {
+			summary.best_ntrans += record.ntrans;
+			summary.best_blocktime += record.blocktime;
+		}
+	}
+	Ok(summary)
+}
+
+/// Figures of a parachain measured through the inclusion of its candidates in the finalized relay
+/// chain blocks, collected by [`inclusion_subscriber`]. The transactions are counted whatever their
+/// dispatch outcome.
+#[derive(Debug, Default, Clone)]
+pub struct InclusionSummary {
+	/// Number of measured transactions in the included parablocks.
+	pub ntrans: u64,
+	/// Sum of the block times of the relay chain blocks including parablocks with measured
+	/// transactions, in milliseconds.
+	pub blocktime: u64,
+	/// Number of relay chain blocks including parablocks with measured transactions.
+	pub nblocks: u64,
+	/// Maximal TPS of a single relay chain block.
+	pub max_tps: f64,
+	/// Number of included parablocks.
+	pub nparablocks: u64,
+	/// Sum and maximum of the time from the timestamps of the parablocks to the timestamps of the
+	/// relay chain blocks including them, in milliseconds.
+	pub latency: (u64, u64),
+}
+
+impl InclusionSummary {
+	/// Average TPS over the relay chain blocks including parablocks with measured transactions.
+	pub fn avg_tps(&self) -> f64 {
+		if self.blocktime == 0 {
+			return 0.0;
+		}
+		self.ntrans as f64 / (self.blocktime as f64 / 1_000_f64)
+	}
+
+	/// Mean inclusion latency of the parablocks, in milliseconds.
+	pub fn mean_latency(&self) -> f64 {
+		self.latency.0 as f64 / self.nparablocks.max(1) as f64
+	}
+}
+
+/// Hash of the parablock of a `ParaInclusion::CandidateIncluded` event, if it belongs to the given
+/// parachain. The event starts with the candidate receipt, whose descriptor begins with the
+/// parachain id and has the same size in all its versions, followed by the head data, i.e. the
+/// encoded header of the parablock.
+fn included_parablock(para_id: u32, mut fields: &[u8]) -> Result<Option<H256>, subxt::Error> {
+	if u32::decode(&mut fields)? != para_id {
+		return Ok(None);
+	}
+	// The rest of the descriptor and the hash of the commitments.
+	<[u8; 320]>::decode(&mut fields)?;
+	let head = Vec::<u8>::decode(&mut fields)?;
+	Ok(Some(H256(blake2_256(&head))))
+}
+
+/// Follows the finalized relay chain blocks until `stop` fires and the block finalized by then has
+/// been processed, scraping the parablocks of the given parachain they include from the collator.
+pub async fn inclusion_subscriber(
+	chain: String,
+	relay_api: OnlineClient<PolkadotConfig>,
+	api: OnlineClient<PolkadotConfig>,
+	para_id: u32,
+	classes: Vec<WorkloadClass>,
+	mut stop: oneshot::Receiver<()>,
+) -> Result<InclusionSummary, subxt::Error> {
+	let counted_calls = classes.iter().map(|c| c.workload.counted_call()).collect::<Vec<_>>();
+	let mut blocks_sub = relay_api.blocks().subscribe_finalized().await?;
+
+	let (mut relay_records, mut para_records) = (HashMap::new(), HashMap::new());
+	let mut finalized_number = 0;
+	let mut stop_at = None;
+	let mut summary = InclusionSummary::default();
+	log::debug!("[{chain}] Starting candidate inclusion watcher");
+	loop {
+		let block = tokio::select! {
+			_ = &mut stop, if stop_at.is_none() => {
+				let finalized: u64 = relay_api.blocks().at_latest().await?.number().into();
+				if finalized_number >= finalized {
+					break;
+				}
+				stop_at = Some(finalized);
+				continue;
+			},
+			block = blocks_sub.next() => match block {
+				Some(block) => block?,
+				None => break,
+			},
+		};
+		let relay = block_record(&relay_api, &[], &mut relay_records, &block).await?;
+		let number: u64 = block.number().into();
+
+		let mut block_ntrans = 0;
+		for ev in block.events().await?.iter() {
+			let ev = ev?;
+			if (ev.pallet_name(), ev.variant_name()) != ("ParaInclusion", "CandidateIncluded") {
+				continue;
+			}
+			let Some(hash) = included_parablock(para_id, ev.field_bytes())? else {
+				continue;
+			};
+			let parablock = match api.blocks().at(hash).await {
+				Ok(parablock) => parablock,
+				Err(e) => {
+					log::warn!("[{chain}] Included parablock {hash:?} not found: {e}");
+					continue;
+				},
+			};
+			let para = block_record(&api, &counted_calls, &mut para_records, &parablock).await?;
+			let latency = relay.timestamp.saturating_sub(para.timestamp);
+			summary.nparablocks += 1;
+			summary.latency.0 += latency;
+			summary.latency.1 = max(summary.latency.1, latency);
+			block_ntrans += para.ntrans;
+			log::debug!(
+				"[{chain}] Parablock {} included in relay block {number} after {latency} ms, {} \
+				 transactions in parablock",
+				parablock.number(),
+				para.ntrans
+			);
+		}
+
+		if block_ntrans > 0 {
+			summary.ntrans += block_ntrans;
+			summary.blocktime += relay.blocktime;
+			summary.nblocks += 1;
+			let block_tps = block_ntrans as f64 / (relay.blocktime as f64 / 1_000_f64);
+			summary.max_tps = f64::max(summary.max_tps, block_tps);
+			log::info!(
+				"[{chain}] {block_ntrans} transactions included in relay block {number}, TPS in \
+				 relay block: {block_tps}"
+			);
+		}
+		finalized_number = number;
+		if stop_at.is_some_and(|n| number >= n) {
+			break;
+		}
+	}
+	Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parity_scale_codec::Encode;
+
+	/// Fields of a `ParaInclusion::CandidateIncluded` event: the candidate receipt, the head data,
+	/// the core index and the group index.
+	fn candidate_included(para_id: u32, head: &[u8]) -> Vec<u8> {
+		let mut fields = para_id.encode();
+		// The rest of the descriptor, from the relay parent to the validation code hash.
+		fields.extend([0xaa; 288]);
+		// The hash of the commitments.
+		fields.extend([0xbb; 32]);
+		fields.extend(head.to_vec().encode());
+		fields.extend(3u32.encode());
+		fields.extend(1u32.encode());
+		fields
+	}
+
+	#[test]
+	fn hrmp_drained_counts_delivered_xcms() {
+		// Ten XCMs concatenated into three HRMP messages: `(nmsgs, delivered, queue_depth)` at
+		// every relay chain block.
+		let blocks =
+			[(None, 0, 2), (None, 0, 3), (Some(10), 4, 1), (Some(10), 10, 1), (Some(10), 10, 0)];
+		let drained = blocks.map(|(nmsgs, delivered, depth)| hrmp_drained(nmsgs, delivered, depth));
+		assert_eq!(drained, [false, false, false, false, true]);
+		// An empty queue before the last XCM is delivered does not stop the sampling.
+		assert!(!hrmp_drained(Some(10), 9, 0));
+		assert!(!hrmp_drained(None, 10, 0));
+	}
+
+	#[test]
+	fn included_parablock_hashes_the_head() {
+		let head = (0..100).collect::<Vec<u8>>();
+		let fields = candidate_included(2000, &head);
+		assert_eq!(included_parablock(2000, &fields).unwrap(), Some(H256(blake2_256(&head))));
+		assert_eq!(included_parablock(2001, &fields).unwrap(), None);
+		// The head data is only found at the end of the 320-byte descriptor and commitments hash.
+		let mut shifted = fields.clone();
+		shifted.remove(4);
+		assert_ne!(included_parablock(2000, &shifted).ok(), Some(Some(H256(blake2_256(&head)))));
+		assert!(included_parablock(2000, &fields[..4 + 320]).is_err());
+	}
+
+	fn millis(ms: &[u64]) -> Latencies {
+		Latencies::new(ms.iter().map(|ms| Duration::from_millis(*ms)).collect())
+	}
+
+	#[test]
+	fn percentiles_of_no_latencies() {
+		let latencies = millis(&[]);
+		assert_eq!(latencies.percentile(0.0), Duration::ZERO);
+		assert_eq!(latencies.percentile(0.5), Duration::ZERO);
+		assert_eq!(latencies.percentile(1.0), Duration::ZERO);
+		assert_eq!(latencies.mean(), Duration::ZERO);
+	}
+
+	#[test]
+	fn percentiles_of_a_single_latency() {
+		let latencies = millis(&[42]);
+		for p in [0.0, 0.5, 0.99, 1.0] {
+			assert_eq!(latencies.percentile(p), Duration::from_millis(42));
+		}
+	}
+
+	#[test]
+	fn percentiles_of_sorted_latencies() {
+		let latencies = millis(&[50, 10, 40, 20, 30]);
+		assert_eq!(latencies.percentile(0.0), Duration::from_millis(10));
+		assert_eq!(latencies.percentile(0.5), Duration::from_millis(30));
+		assert_eq!(latencies.percentile(1.0), Duration::from_millis(50));
+		assert_eq!(latencies.mean(), Duration::from_millis(30));
+
+		let latencies = millis(&(1..=100).collect::<Vec<_>>());
+		assert_eq!(latencies.percentile(0.0), Duration::from_millis(1));
+		assert_eq!(latencies.percentile(0.99), Duration::from_millis(99));
+		assert_eq!(latencies.percentile(1.0), Duration::from_millis(100));
+	}
+}
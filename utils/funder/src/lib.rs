@@ -1,6 +1,17 @@
 use sp_core::{sr25519::Pair as SrPair, Pair};
 
+/// Derives `n` sr25519 accounts as `{seed}/{i}`.
 pub fn derive_accounts(n: usize, seed: String) -> Vec<SrPair> {
+	derive(n, seed, "/")
+}
+
+/// Derives `n` key pairs of any type as `{seed}//{i}`. Hard junctions are used as not every key
+/// type supports soft derivation.
+pub fn derive_pairs<P: Pair>(n: usize, seed: String) -> Vec<P> {
+	derive(n, seed, "//")
+}
+
+fn derive<P: Pair>(n: usize, seed: String, junction: &'static str) -> Vec<P> {
 	let t = std::cmp::min(
 		n,
 		std::thread::available_parallelism().unwrap_or(1usize.try_into().unwrap()).get(),
@@ -17,8 +28,8 @@ pub fn derive_accounts(n: usize, seed: String) -> Vec<SrPair> {
 			chunk
 				.into_iter()
 				.map(move |i| {
-					let derivation = format!("{seed}/{i}");
-					<SrPair as Pair>::from_string(&derivation, None).unwrap()
+					let derivation = format!("{seed}{junction}{i}");
+					<P as Pair>::from_string(&derivation, None).unwrap()
 				})
 				.collect::<Vec<_>>()
 		}));
@@ -0,0 +1,100 @@
+//! Signing for Frontier/EVM-compatible chains, whose accounts are 20-byte Ethereum addresses
+//! derived from ECDSA-secp256k1 public keys and whose transactions carry Ethereum signatures.
+
+use codec::{Decode, Encode};
+use sha3::{Digest, Keccak256};
+use sp_core::{ecdsa, Pair};
+use subxt::{
+	config::{DefaultExtrinsicParams, SubstrateConfig},
+	tx::Signer,
+};
+
+/// Chain configuration of Moonbeam-style chains.
+pub enum EthConfig {}
+
+impl subxt::Config for EthConfig {
+	type AccountId = AccountId20;
+	type Address = AccountId20;
+	type Signature = EthereumSignature;
+	type Hasher = <SubstrateConfig as subxt::Config>::Hasher;
+	type Header = <SubstrateConfig as subxt::Config>::Header;
+	type ExtrinsicParams = DefaultExtrinsicParams<Self>;
+	type AssetId = u32;
+}
+
+/// Ethereum address, used as the account id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode, serde::Serialize)]
+pub struct AccountId20(pub [u8; 20]);
+
+impl AccountId20 {
+	/// Address of the given public key, i.e. the last 20 bytes of the keccak hash of its
+	/// uncompressed form.
+	pub fn from_public(public: &ecdsa::Public) -> Self {
+		let public = libsecp256k1::PublicKey::parse_compressed(&public.0)
+			.expect("A valid public key is always decompressible");
+		// Skip the 0x04 tag of the uncompressed key.
+		let hash = Keccak256::digest(&public.serialize()[1..]);
+		let mut account = [0u8; 20];
+		account.copy_from_slice(&hash[12..]);
+		Self(account)
+	}
+}
+
+impl std::fmt::Display for AccountId20 {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "0x")?;
+		self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+	}
+}
+
+/// Recoverable ECDSA signature over the keccak hash of the signer payload.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct EthereumSignature(pub [u8; 65]);
+
+#[derive(Clone)]
+pub struct EthSigner {
+	account_id: AccountId20,
+	signer: ecdsa::Pair,
+}
+
+impl EthSigner {
+	/// Creates a new [`Signer`] from an [`sp_core::ecdsa::Pair`].
+	pub fn new(signer: ecdsa::Pair) -> Self {
+		Self { account_id: AccountId20::from_public(&signer.public()), signer }
+	}
+
+	/// Return the account ID.
+	pub fn account_id(&self) -> &AccountId20 {
+		&self.account_id
+	}
+}
+
+impl Signer<EthConfig> for EthSigner {
+	fn account_id(&self) -> AccountId20 {
+		self.account_id
+	}
+
+	fn sign(&self, signer_payload: &[u8]) -> EthereumSignature {
+		let hash: [u8; 32] = Keccak256::digest(signer_payload).into();
+		EthereumSignature(self.signer.sign_prehashed(&hash).0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn address_of_alith() {
+		// Well-known development account of Moonbeam and Frontier chains.
+		let alith = ecdsa::Pair::from_string(
+			"0x5fb92d6e98884f76de468fa3f6278f8807c48bebc13595d45af5bdc4da702133",
+			None,
+		)
+		.unwrap();
+		assert_eq!(
+			AccountId20::from_public(&alith.public()).to_string(),
+			"0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac"
+		);
+	}
+}
@@ -0,0 +1,152 @@
+//! Key pairs of the benchmark accounts. Every key type comes with its own account id format and
+//! transaction signature, hidden behind [`Keypair`] so that the rest of the harness does not care
+//! which one is in use.
+
+use crate::{
+	eth::{EthConfig, EthSigner},
+	MultiPairSigner, PairSigner, SignedTx, TxParams,
+};
+use serde::{Deserialize, Serialize};
+use sp_core::{crypto::Ss58Codec, ecdsa, ed25519, sr25519, Pair};
+use subxt::{
+	config::{
+		Config, DefaultExtrinsicParams, DefaultExtrinsicParamsBuilder, ExtrinsicParams, Hasher,
+	},
+	tx::{DynamicPayload, SubmittableTransaction},
+	utils::H256,
+	OfflineClient, OnlineClient, PolkadotConfig,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyType {
+	/// sr25519 keys and `AccountId32`, as on Polkadot SDK chains.
+	Sr25519,
+	/// ed25519 keys and `AccountId32`, with `MultiSignature::Ed25519` signatures.
+	Ed25519,
+	/// ECDSA-secp256k1 keys and `AccountId32`, with `MultiSignature::Ecdsa` signatures.
+	Ecdsa,
+	/// ECDSA-secp256k1 keys and keccak-derived `AccountId20`, as on Frontier/EVM-compatible chains.
+	#[value(skip)]
+	Ethereum,
+}
+
+impl KeyType {
+	/// Derives `n` key pairs from `seed`. Only sr25519 keys are derived with soft junctions, as the
+	/// other key types do not support them.
+	pub fn derive(&self, n: usize, seed: &str) -> Vec<Keypair> {
+		match self {
+			KeyType::Sr25519 => funder_lib::derive_accounts(n, seed.to_owned())
+				.into_iter()
+				.map(Keypair::Sr25519)
+				.collect(),
+			KeyType::Ed25519 => funder_lib::derive_pairs(n, seed.to_owned())
+				.into_iter()
+				.map(Keypair::Ed25519)
+				.collect(),
+			KeyType::Ecdsa => funder_lib::derive_pairs(n, seed.to_owned())
+				.into_iter()
+				.map(Keypair::Ecdsa)
+				.collect(),
+			KeyType::Ethereum => funder_lib::derive_pairs::<ecdsa::Pair>(n, seed.to_owned())
+				.into_iter()
+				.map(|pair| Keypair::Ethereum(EthSigner::new(pair)))
+				.collect(),
+		}
+	}
+}
+
+#[derive(Clone)]
+pub enum Keypair {
+	Sr25519(sr25519::Pair),
+	Ed25519(ed25519::Pair),
+	Ecdsa(ecdsa::Pair),
+	Ethereum(EthSigner),
+}
+
+impl Keypair {
+	/// Raw account id bytes, as passed to the workloads and used as storage keys.
+	pub fn account_id(&self) -> Vec<u8> {
+		match self {
+			Keypair::Sr25519(pair) => pair.public().0.to_vec(),
+			Keypair::Ed25519(pair) => pair.public().0.to_vec(),
+			Keypair::Ecdsa(pair) => MultiPairSigner::new(pair.clone()).account_id().0.to_vec(),
+			Keypair::Ethereum(signer) => signer.account_id().0.to_vec(),
+		}
+	}
+
+	/// Human readable address, in the format expected by the `Balances` genesis config: SS58 for
+	/// 32-byte accounts and 0x-prefixed hex for Ethereum ones.
+	pub fn address(&self, ss58_prefix: u16) -> String {
+		match self {
+			Keypair::Sr25519(pair) => pair.public().to_ss58check_with_version(ss58_prefix.into()),
+			Keypair::Ed25519(pair) => pair.public().to_ss58check_with_version(ss58_prefix.into()),
+			Keypair::Ecdsa(_) => {
+				let account_id = <[u8; 32]>::try_from(self.account_id()).expect("AccountId32");
+				sp_core::crypto::AccountId32::from(account_id)
+					.to_ss58check_with_version(ss58_prefix.into())
+			},
+			Keypair::Ethereum(signer) => signer.account_id().to_string(),
+		}
+	}
+
+	/// Signs `tx_call` with the given `params`. Transactions of every key type are returned as
+	/// [`SignedTx`], as submitting them does not depend on how they were signed.
+	pub fn sign(
+		&self,
+		api: &OnlineClient<PolkadotConfig>,
+		tx_call: &DynamicPayload,
+		params: &TxParams,
+	) -> SignedTx {
+		match self {
+			Keypair::Sr25519(pair) => {
+				let tx_params = extrinsic_params::<PolkadotConfig>(params);
+				api.tx()
+					.create_partial_offline(tx_call, tx_params)
+					.expect("Failed to create partial offline transaction")
+					.sign(&PairSigner::new(pair.clone()))
+			},
+			Keypair::Ed25519(pair) => api
+				.tx()
+				.create_partial_offline(tx_call, extrinsic_params::<PolkadotConfig>(params))
+				.expect("Failed to create partial offline transaction")
+				.sign(&MultiPairSigner::new(pair.clone())),
+			Keypair::Ecdsa(pair) => api
+				.tx()
+				.create_partial_offline(tx_call, extrinsic_params::<PolkadotConfig>(params))
+				.expect("Failed to create partial offline transaction")
+				.sign(&MultiPairSigner::new(pair.clone())),
+			Keypair::Ethereum(signer) => {
+				let eth_api = OfflineClient::<EthConfig>::new(
+					api.genesis_hash(),
+					api.runtime_version(),
+					api.metadata(),
+				);
+				let tx_params = extrinsic_params::<EthConfig>(params);
+				let tx = eth_api
+					.tx()
+					.create_partial_offline(tx_call, tx_params)
+					.expect("Failed to create partial offline transaction")
+					.sign(signer);
+				SubmittableTransaction::from_bytes(api.clone(), tx.into_encoded())
+			},
+		}
+	}
+}
+
+/// Params of the default signed extensions, for chains hashing with blake2-256 like the Polkadot
+/// SDK ones.
+fn extrinsic_params<T>(
+	params: &TxParams,
+) -> <DefaultExtrinsicParams<T> as ExtrinsicParams<T>>::Params
+where
+	T: Config,
+	T::Hasher: Hasher<Output = H256>,
+{
+	let mut builder = DefaultExtrinsicParamsBuilder::<T>::new().nonce(params.nonce).tip(params.tip);
+	if let Some(m) = params.mortality {
+		builder = builder.mortal_from_unchecked(m.period, m.block_number, m.block_hash);
+	}
+	builder.build()
+}
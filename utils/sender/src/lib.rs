@@ -1,20 +1,32 @@
-use futures::{stream::FuturesUnordered, StreamExt};
+use futures::StreamExt;
+use keypair::Keypair;
 use log::*;
 use sp_core::{
 	sr25519::{self, Pair as SrPair},
 	Pair,
 };
 use sp_runtime::{
+	generic::Era,
 	traits::{IdentifyAccount, Verify},
-	MultiSignature,
+	MultiSignature, MultiSigner,
+};
+use std::{
+	error::Error,
+	sync::Arc,
+	time::{Duration, Instant},
 };
-use std::{error::Error, time::Duration};
 use subxt::{
-	config::{polkadot::PolkadotExtrinsicParamsBuilder as Params, substrate::AccountId32},
-	dynamic::Value,
-	tx::{Signer, SubmittableTransaction},
+	config::substrate::AccountId32,
+	tx::{DynamicPayload, Signer, SubmittableTransaction},
+	utils::H256,
 	OnlineClient, PolkadotConfig,
 };
+use tokio::sync::watch;
+use workload::{BalanceTransfer, Workload};
+
+pub mod eth;
+pub mod keypair;
+pub mod workload;
 
 /// Maximal number of connection attempts.
 const MAX_ATTEMPTS: usize = 10;
@@ -43,23 +55,140 @@ pub async fn connect(url: &str) -> Result<OnlineClient<PolkadotConfig>, Box<dyn
 
 pub type SignedTx = SubmittableTransaction<PolkadotConfig, OnlineClient<PolkadotConfig>>;
 
-pub fn sign_txs<P, S, C>(params: impl Iterator<Item = P>, signer: S) -> Vec<SignedTx>
+/// Per-transaction parameters of the signed extensions.
+#[derive(Debug, Clone, Default)]
+pub struct TxParams {
+	pub nonce: u64,
+	/// Tip paid to the block author, raising the priority of the transaction in the pool.
+	pub tip: u128,
+	/// Era of a mortal transaction, which is immortal otherwise.
+	pub mortality: Option<Mortality>,
+}
+
+/// Era of a mortal transaction, which is only valid for `period` blocks from the given block,
+/// rounded up to a power of two by the runtime.
+#[derive(Debug, Clone, Copy)]
+pub struct Mortality {
+	pub period: u64,
+	pub block_number: u64,
+	pub block_hash: H256,
+}
+
+impl Mortality {
+	/// Number of the first block in which the transaction is no longer valid, as its era has ended.
+	pub fn death(&self) -> u64 {
+		Era::mortal(self.period, self.block_number).death(self.block_number)
+	}
+}
+
+impl TxParams {
+	/// Parameters of a transaction with the given nonce and no tip.
+	pub fn new(nonce: u64) -> Self {
+		Self { nonce, ..Default::default() }
+	}
+}
+
+/// Stage of a submitted transaction reported by its status stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStage {
+	Validated,
+	Broadcasted,
+	InBestBlock,
+	InFinalizedBlock,
+}
+
+impl TxStage {
+	pub const ALL: [TxStage; 4] =
+		[Self::Validated, Self::Broadcasted, Self::InBestBlock, Self::InFinalizedBlock];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Validated => "Validated",
+			Self::Broadcasted => "Broadcasted",
+			Self::InBestBlock => "InBestBlock",
+			Self::InFinalizedBlock => "InFinalizedBlock",
+		}
+	}
+}
+
+/// Times at which a submitted transaction went through the stages reported by its status stream.
+/// Each stage is timed the first time it is reported.
+#[derive(Debug, Clone)]
+pub struct TxTimes {
+	/// Time at which the transaction was submitted to the node.
+	pub submitted: Instant,
+	pub validated: Option<Instant>,
+	pub broadcasted: Option<Instant>,
+	pub in_best_block: Option<Instant>,
+	pub in_finalized_block: Option<Instant>,
+	/// Time at which the transaction was first reported in a block, best or finalized.
+	pub included: Option<Instant>,
+	/// Why the transaction was rejected, dropped or found invalid, if it was never finalized.
+	pub dropped: Option<String>,
+	/// Number of the best block when the transaction was rejected, dropped or found invalid.
+	pub dropped_at: Option<u64>,
+	/// Whether the node refused the transaction on submission.
+	pub rejected: bool,
+	/// Whether the transaction was rejected or found invalid by the pool.
+	pub invalid: bool,
+	/// Whether the transaction was still being submitted at the deadline, so that the node never
+	/// accepted it.
+	pub pending: bool,
+}
+
+impl TxTimes {
+	fn new(submitted: Instant) -> Self {
+		Self {
+			submitted,
+			validated: None,
+			broadcasted: None,
+			in_best_block: None,
+			in_finalized_block: None,
+			included: None,
+			dropped: None,
+			dropped_at: None,
+			rejected: false,
+			invalid: false,
+			pending: false,
+		}
+	}
+
+	/// Time from the submission of the transaction to its inclusion, if it was included.
+	pub fn inclusion_latency(&self) -> Option<Duration> {
+		self.included.map(|included| included - self.submitted)
+	}
+
+	/// Time from the submission of the transaction to the given stage, if it was reached.
+	pub fn latency(&self, stage: TxStage) -> Option<Duration> {
+		let reached = match stage {
+			TxStage::Validated => self.validated,
+			TxStage::Broadcasted => self.broadcasted,
+			TxStage::InBestBlock => self.in_best_block,
+			TxStage::InFinalizedBlock => self.in_finalized_block,
+		};
+		reached.map(|reached| reached - self.submitted)
+	}
+}
+
+/// Signs the transactions built from `params` on all the available threads, each signing a
+/// contiguous chunk of them, and returns them in the order of `params`.
+pub fn sign_txs<P, S, T>(params: impl Iterator<Item = P>, signer: S) -> Vec<T>
 where
 	P: Send + 'static,
-	S: Fn(P) -> SignedTx + Send + Sync + 'static,
-	C: subxt::Config,
+	S: Fn(P) -> T + Send + Sync + 'static,
+	T: Send + 'static,
 {
 	let t = std::thread::available_parallelism().unwrap_or(1usize.try_into().unwrap()).get();
 
-	let mut tn = (0..t).cycle();
-	let mut tranges: Vec<_> = (0..t).map(|_| Vec::new()).collect();
-	params.for_each(|p| tranges[tn.next().unwrap()].push(p));
+	let params: Vec<_> = params.collect();
+	let chunk_size = params.len().div_ceil(t).max(1);
+	let mut params = params.into_iter();
 	let mut threads = Vec::new();
 
 	let signer = std::sync::Arc::new(signer);
 
-	tranges.into_iter().for_each(|chunk| {
-		// let api = api.clone();
+	(0..t).for_each(|_| {
+		let chunk: Vec<_> = params.by_ref().take(chunk_size).collect();
 		let signer = signer.clone();
 		threads
 			.push(std::thread::spawn(move || chunk.into_iter().map(&*signer).collect::<Vec<_>>()));
@@ -115,53 +244,205 @@ impl Signer<PolkadotConfig> for PairSigner {
 	}
 }
 
+/// [`Signer`] of any key type with a [`MultiSignature`] variant, such as ed25519 and ECDSA ones.
+/// The account id is derived from the public key as by `MultiSigner`, i.e. it is the public key
+/// itself, or its blake2-256 hash for ECDSA.
+#[derive(Clone)]
+pub struct MultiPairSigner<P> {
+	account_id: AccountId32,
+	signer: P,
+}
+
+impl<P> MultiPairSigner<P>
+where
+	P: Pair,
+	MultiSigner: From<P::Public>,
+{
+	pub fn new(signer: P) -> Self {
+		let account_id = MultiSigner::from(signer.public()).into_account();
+		Self { account_id: AccountId32(account_id.into()), signer }
+	}
+
+	/// Return the account ID.
+	pub fn account_id(&self) -> &AccountId32 {
+		&self.account_id
+	}
+}
+
+impl<P> Signer<PolkadotConfig> for MultiPairSigner<P>
+where
+	P: Pair,
+	MultiSignature: From<P::Signature>,
+{
+	fn account_id(&self) -> <PolkadotConfig as subxt::Config>::AccountId {
+		self.account_id.clone()
+	}
+
+	fn sign(&self, signer_payload: &[u8]) -> <PolkadotConfig as subxt::Config>::Signature {
+		match MultiSignature::from(self.signer.sign(signer_payload)) {
+			MultiSignature::Ed25519(signature) =>
+				subxt::utils::MultiSignature::Ed25519(signature.0),
+			MultiSignature::Sr25519(signature) =>
+				subxt::utils::MultiSignature::Sr25519(signature.0),
+			MultiSignature::Ecdsa(signature) => subxt::utils::MultiSignature::Ecdsa(signature.0),
+		}
+	}
+}
+
+/// Signs the setup transactions of a workload, one per `((sender, nonce), call)` as returned by
+/// [`Workload::setup_calls`].
+pub fn sign_setup_txs(
+	api: OnlineClient<PolkadotConfig>,
+	calls: impl Iterator<Item = ((Keypair, u64), DynamicPayload)>,
+) -> Vec<SignedTx> {
+	sign_txs(calls, move |((sender, nonce), tx_call)| {
+		sender.sign(&api, &tx_call, &TxParams::new(nonce))
+	})
+}
+
+/// Signs the measured transactions of the workload, one per `((sender, params), receiver)`.
+pub fn sign_workload_txs(
+	api: OnlineClient<PolkadotConfig>,
+	workload: Arc<dyn Workload>,
+	pairs: impl Iterator<Item = ((Keypair, TxParams), Keypair)>,
+) -> Vec<SignedTx> {
+	sign_txs(pairs, move |((sender, params), receiver)| {
+		let tx_call = workload.call(&sender.account_id(), &receiver.account_id());
+		sender.sign(&api, &tx_call, &params)
+	})
+}
+
 pub fn sign_balance_transfers(
 	api: OnlineClient<PolkadotConfig>,
 	pairs: impl Iterator<Item = ((SrPair, u64), SrPair)>,
 ) -> Vec<SignedTx> {
-	sign_txs::<_, _, PolkadotConfig>(pairs, move |((sender, nonce), receiver)| {
-		let signer = PairSigner::new(sender);
-		let tx_params = Params::new().nonce(nonce).build();
-		let tx_call = subxt::dynamic::tx(
-			"Balances",
-			"transfer_keep_alive",
-			vec![
-				Value::unnamed_variant("Id", [Value::from_bytes(receiver.public())]),
-				Value::u128(1u32.into()),
-			],
-		);
-		api.tx()
-			.create_partial_offline(&tx_call, tx_params)
-			.expect("Failed to create partial offline transaction")
-			.sign(&signer)
-	})
+	sign_workload_txs(
+		api,
+		Arc::new(BalanceTransfer::default()),
+		pairs.map(|((sender, nonce), receiver)| {
+			((Keypair::Sr25519(sender), TxParams::new(nonce)), Keypair::Sr25519(receiver))
+		}),
+	)
 }
 
-/// Here the signed extrinsics are submitted.
+/// Here the signed extrinsics are submitted. The status of every transaction is watched until it
+/// is finalized or dropped, or until the deadline, and the times of its stages are returned, in the
+/// order of `txs`. The transactions whose submission is still pending at the deadline are marked
+/// as pending, as the node never accepted them. The number of the best block published to `best`
+/// is recorded when a transaction is dropped.
 pub async fn submit_txs(
 	txs: Vec<SubmittableTransaction<PolkadotConfig, OnlineClient<PolkadotConfig>>>,
-) -> Result<(), Box<dyn Error>> {
-	let futs = txs.iter().map(|tx| tx.submit_and_watch()).collect::<FuturesUnordered<_>>();
-	let res = futs.collect::<Vec<_>>().await;
-	let res: Result<Vec<_>, _> = res.into_iter().collect();
-	let res = res.expect("All the transactions submitted successfully");
-	let mut statuses = futures::stream::select_all(res);
-	while let Some(a) = statuses.next().await {
+	deadline: Option<Instant>,
+	best: &watch::Receiver<u64>,
+) -> Result<Vec<TxTimes>, Box<dyn Error>> {
+	// The latencies include the round trip of the submission itself.
+	let futs = txs.iter().map(|tx| async move {
+		let submitted = Instant::now();
+		let progress = match deadline {
+			Some(deadline) =>
+				tokio::time::timeout_at(deadline.into(), tx.submit_and_watch()).await.ok(),
+			None => Some(tx.submit_and_watch().await),
+		};
+		(submitted, progress)
+	});
+	let res = futures::future::join_all(futs).await;
+	let pending = res.iter().filter(|(_, progress)| progress.is_none()).count();
+	if pending > 0 {
+		warn!("Deadline reached with {pending} transactions still being submitted");
+	}
+	// Transactions may be rejected on submission, e.g. when their era has already expired.
+	let mut times = res
+		.iter()
+		.map(|(submitted, progress)| match progress {
+			Some(Ok(_)) => TxTimes::new(*submitted),
+			None => TxTimes { pending: true, ..TxTimes::new(*submitted) },
+			Some(Err(e)) => {
+				log::debug!("Transaction rejected: {e}");
+				TxTimes {
+					dropped: Some(e.to_string()),
+					dropped_at: Some(*best.borrow()),
+					rejected: true,
+					invalid: true,
+					..TxTimes::new(*submitted)
+				}
+			},
+		})
+		.collect::<Vec<_>>();
+	let mut statuses = futures::stream::select_all(
+		res.into_iter()
+			.enumerate()
+			.filter_map(|(i, (_, progress))| progress?.ok().map(|progress| (i, progress)))
+			.map(|(i, progress)| progress.map(move |status| (i, status))),
+	);
+	loop {
+		let next = match deadline {
+			Some(deadline) => tokio::time::timeout_at(deadline.into(), statuses.next()).await,
+			None => Ok(statuses.next().await),
+		};
+		let Ok(next) = next else {
+			warn!("Deadline reached with {} transactions still watched", statuses.len());
+			break;
+		};
+		let Some((i, a)) = next else {
+			break;
+		};
+		let now = Instant::now();
+		let best_number = *best.borrow();
 		match a {
 			Ok(st) => match st {
-				subxt::tx::TxStatus::Validated => log::trace!("VALIDATED"),
-				subxt::tx::TxStatus::Broadcasted => log::trace!("BROADCASTED"),
+				subxt::tx::TxStatus::Validated => {
+					log::trace!("VALIDATED");
+					times[i].validated.get_or_insert(now);
+				},
+				subxt::tx::TxStatus::Broadcasted => {
+					log::trace!("BROADCASTED");
+					times[i].broadcasted.get_or_insert(now);
+				},
 				subxt::tx::TxStatus::NoLongerInBestBlock => log::warn!("NO LONGER IN BEST BLOCK"),
-				subxt::tx::TxStatus::InBestBlock(_) => log::trace!("IN BEST BLOCK"),
-				subxt::tx::TxStatus::InFinalizedBlock(_) => log::trace!("IN FINALIZED BLOCK"),
-				subxt::tx::TxStatus::Error { message } => log::warn!("ERROR: {message}"),
-				subxt::tx::TxStatus::Invalid { message } => log::trace!("INVALID: {message}"),
-				subxt::tx::TxStatus::Dropped { message } => log::trace!("DROPPED: {message}"),
+				subxt::tx::TxStatus::InBestBlock(_) => {
+					log::trace!("IN BEST BLOCK");
+					times[i].in_best_block.get_or_insert(now);
+					times[i].included.get_or_insert(now);
+				},
+				subxt::tx::TxStatus::InFinalizedBlock(_) => {
+					log::trace!("IN FINALIZED BLOCK");
+					times[i].in_finalized_block.get_or_insert(now);
+					times[i].included.get_or_insert(now);
+				},
+				subxt::tx::TxStatus::Error { message } => {
+					log::warn!("ERROR: {message}");
+					times[i].dropped = Some(message);
+					times[i].dropped_at = Some(best_number);
+				},
+				subxt::tx::TxStatus::Invalid { message } => {
+					log::trace!("INVALID: {message}");
+					times[i].dropped = Some(message);
+					times[i].dropped_at = Some(best_number);
+					times[i].invalid = true;
+				},
+				subxt::tx::TxStatus::Dropped { message } => {
+					log::trace!("DROPPED: {message}");
+					times[i].dropped = Some(message);
+					times[i].dropped_at = Some(best_number);
+				},
 			},
 			Err(e) => {
 				warn!("Error status {:?}", e);
+				times[i].dropped = Some(e.to_string());
+				times[i].dropped_at = Some(best_number);
 			},
 		}
 	}
-	Ok(())
+	Ok(times)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sign_txs_keeps_order() {
+		let n = 4 * std::thread::available_parallelism().map_or(1, |t| t.get()) + 3;
+		assert_eq!(sign_txs(0..n, |i| i * 2), (0..n).map(|i| i * 2).collect::<Vec<_>>());
+	}
 }
@@ -13,6 +13,7 @@ use std::{
 use sp_core::{sr25519::Pair as SrPair, Pair};
 use subxt::{
 	blocks::BlockRef,
+	events::Phase,
 	config::polkadot::PolkadotExtrinsicParamsBuilder as Params,
 	dynamic::Value,
 	ext::scale_value::{Primitive, ValueDef},
@@ -21,22 +22,21 @@ use subxt::{
 };
 use tokio::sync::RwLock;
 
-use sender_lib::PairSigner;
+use sender_lib::{
+	workload::{BalanceTransfer, Workload},
+	PairSigner,
+};
 
 const SENDER_SEED: &str = "//Sender";
 const RECEIVER_SEED: &str = "//Receiver";
 const ALICE_SEED: &str = "//Alice";
 
-/// Amount to send in each transaction, small so that we can do many transactions before
-/// running out of funds.
-const SMALL_TOKEN_AMOUNT: Value =
-	Value { value: ValueDef::Primitive(Primitive::U128(1)), context: () };
-
 /// Amount to seed each sender with, largest possible value so that we do not run out of funds.
 const BIG_TOKEN_AMOUNT: Value =
 	Value { value: ValueDef::Primitive(Primitive::U128(u128::MAX)), context: () };
 
-/// Util program to send transactions
+/// Util program to send transactions. It only sends balance transfers; the other workloads, which
+/// may need setup phases, are run by the `stps` binary.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -220,6 +220,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 					.map(PairSigner::new)
 					.collect::<Vec<_>>();
 
+				let workload: Arc<dyn Workload> = Arc::new(BalanceTransfer::default());
+
 				info!("Starting senders");
 
 				// Overall metrics that we use to throttle
@@ -249,6 +251,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 						let in_block = in_block.clone();
 
 						let api = api.clone();
+						let workload = workload.clone();
 						let nrecv = if args.batch > 1 { args.batch } else { 1 };
 						let receiver_accounts = receiver_accounts.clone();
 
@@ -278,14 +281,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 								let tx_payload = if args.batch > 1 {
 									let calls = (0..args.batch).map(|i|
-										subxt::dynamic::tx(
-											"Balances",
-											"transfer_keep_alive",
-											vec![
-												Value::unnamed_variant("Id", [Value::from_bytes(receivers[i].public())]),
-												SMALL_TOKEN_AMOUNT,
-											],
-										).into_value()
+										workload.call(sender.public().as_ref(), receivers[i].public().as_ref()).into_value()
 									).collect::<Vec<_>>();
 
 									subxt::dynamic::tx(
@@ -294,14 +290,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 										vec![ Value::named_composite(vec![("calls", calls.into())]) ]
 									)
 								} else {
-									subxt::dynamic::tx(
-										"Balances",
-										"transfer_keep_alive",
-										vec![
-											Value::unnamed_variant("Id", [Value::from_bytes(receivers[0].public())]),
-											SMALL_TOKEN_AMOUNT,
-										],
-									)
+									workload.call(sender.public().as_ref(), receivers[0].public().as_ref())
 								};
 								log::debug!("Sender {} using nonce {}", i, nonce);
 								let tx_params = Params::new().nonce(nonce as u64).build();
@@ -366,6 +355,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 						};
 
 						let mut txcount = 0;
+						// Measured extrinsics emitting no event of their own, counted once dispatched.
+						let mut counted = Vec::new();
 
 						for ex in extrinsics.iter() {
 							match (ex.pallet_name().expect("pallet name"), ex.variant_name().expect("variant name")) {
@@ -374,19 +365,24 @@ fn main() -> Result<(), Box<dyn Error>> {
 									block_time =  new_timestamp - timestamp;
 									timestamp = new_timestamp;
 								},
+								// NFT transfers emit no `Balances::Transfer` event, so they are
+								// counted by extrinsic, as they always were.
 								("Nfts", "transfer") => {
 									txcount += 1;
 								},
+								call if workload.counted_event().is_none() && call == workload.counted_call() => {
+									counted.push(ex.index());
+								},
 								_ => (),
 							}
 						}
 
 						for ev in best_block.events().await.expect("Events are available").iter() {
 							let ev = ev.expect("Event is available");
-							match (ev.pallet_name(), ev.variant_name()) {
-								("Balances", "Transfer") => {
-									txcount += 1;
-								},
+							let event = (ev.pallet_name(), ev.variant_name());
+							match (workload.counted_event(), ev.phase()) {
+								(Some(counted_event), _) if event == counted_event => txcount += 1,
+								(None, Phase::ApplyExtrinsic(i)) if event == ("System", "ExtrinsicSuccess") && counted.contains(&i) => txcount += 1,
 								_ => (),
 							}
 						}
@@ -0,0 +1,109 @@
+use super::{lookup, SetupPhase, Workload};
+use sp_core::hashing::blake2_256;
+use std::{
+	collections::HashMap,
+	sync::{
+		atomic::{AtomicU32, Ordering},
+		RwLock,
+	},
+};
+use subxt::{dynamic::Value, tx::DynamicPayload};
+
+const CREATE: usize = 0;
+const MINT: usize = 1;
+
+/// `Assets::transfer_keep_alive` of a fungible asset.
+///
+/// With a non-sufficient asset, every sender creates its own asset and mints it to itself
+/// beforehand, with consecutive ids from an offset of `asset_id` derived from a seed unique to the
+/// run, so that the assets of previous runs on the same network are not reused. Sufficient assets
+/// can only be created by root, so a single sufficient asset `asset_id` is expected to exist and to
+/// be held by every sender already, e.g. from genesis, and there is no setup.
+pub struct AssetTransfer {
+	/// Id of the sufficient asset, or base id of the per-sender non-sufficient assets.
+	pub asset_id: u32,
+	/// Whether the asset is sufficient, i.e. whether holding it is enough for an account to exist.
+	pub sufficient: bool,
+	/// Amount to send in each transaction.
+	pub amount: u128,
+	/// Next non-sufficient asset id to assign.
+	next_id: AtomicU32,
+	/// Non-sufficient asset created by each sender, assigned when signing the first setup phase.
+	assets: RwLock<HashMap<Vec<u8>, u32>>,
+}
+
+impl AssetTransfer {
+	/// Amount of the asset every sender holds before the transfers, enough for any number of them.
+	pub const MINTED: u128 = 1_000_000_000_000;
+
+	/// Creates the workload, with the non-sufficient asset ids offset by the hash of `seed`.
+	pub fn new(asset_id: u32, sufficient: bool, seed: &str) -> Self {
+		let hash = blake2_256(seed.as_bytes());
+		let offset = u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]);
+		Self {
+			asset_id,
+			sufficient,
+			amount: 1,
+			next_id: AtomicU32::new(asset_id.wrapping_add(offset)),
+			assets: Default::default(),
+		}
+	}
+
+	fn asset(&self, owner: &[u8]) -> Value {
+		let id = if self.sufficient {
+			self.asset_id
+		} else {
+			let assets = self.assets.read().expect("Lock is not poisoned");
+			*assets.get(owner).expect("Asset exists")
+		};
+		Value::u128(id.into())
+	}
+}
+
+impl Workload for AssetTransfer {
+	fn setup_phases(&self) -> Vec<SetupPhase> {
+		if self.sufficient {
+			return Vec::new();
+		}
+		vec![
+			SetupPhase { name: "Created assets", event: ("Assets", "Created") },
+			SetupPhase { name: "Minted assets", event: ("Assets", "Issued") },
+		]
+	}
+
+	fn setup_call(&self, phase: usize, sender: &[u8]) -> DynamicPayload {
+		match phase {
+			CREATE => {
+				let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+				self.assets.write().expect("Lock is not poisoned").insert(sender.to_vec(), id);
+				subxt::dynamic::tx(
+					"Assets",
+					"create",
+					vec![Value::u128(id.into()), lookup(sender), Value::u128(1)],
+				)
+			},
+			MINT => subxt::dynamic::tx(
+				"Assets",
+				"mint",
+				vec![self.asset(sender), lookup(sender), Value::u128(Self::MINTED)],
+			),
+			_ => unreachable!("AssetTransfer has two setup phases"),
+		}
+	}
+
+	fn call(&self, sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		subxt::dynamic::tx(
+			"Assets",
+			"transfer_keep_alive",
+			vec![self.asset(sender), lookup(receiver), Value::u128(self.amount)],
+		)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		("Assets", "transfer_keep_alive")
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some(("Assets", "Transferred"))
+	}
+}
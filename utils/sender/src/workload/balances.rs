@@ -0,0 +1,74 @@
+use super::{lookup, Workload};
+use std::sync::OnceLock;
+use subxt::{dynamic::Value, tx::DynamicPayload, OnlineClient, PolkadotConfig};
+
+/// Standard `Balances::transfer_keep_alive` from one pre-existing account to another.
+pub struct BalanceTransfer {
+	/// Amount to send in each transaction, small so that many transactions can be sent before
+	/// running out of funds.
+	pub amount: u128,
+}
+
+impl Default for BalanceTransfer {
+	fn default() -> Self {
+		Self { amount: 1 }
+	}
+}
+
+impl Workload for BalanceTransfer {
+	fn call(&self, _sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		subxt::dynamic::tx(
+			"Balances",
+			"transfer_keep_alive",
+			vec![lookup(receiver), Value::u128(self.amount)],
+		)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		("Balances", "transfer_keep_alive")
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some(("Balances", "Transfer"))
+	}
+}
+
+/// `Balances::transfer_keep_alive` of the existential deposit to a fresh account, which creates
+/// the account and endows it. The existential deposit is read from the metadata of the chain.
+#[derive(Default)]
+pub struct FreshTransfer {
+	existential_deposit: OnceLock<u128>,
+}
+
+impl Workload for FreshTransfer {
+	fn prepare(&self, api: &OnlineClient<PolkadotConfig>) -> Result<(), subxt::Error> {
+		let addr = subxt::dynamic::constant("Balances", "ExistentialDeposit");
+		let deposit = api.constants().at(&addr)?.to_value()?.as_u128();
+		let deposit =
+			deposit.ok_or_else(|| subxt::Error::Other("Invalid ExistentialDeposit".into()))?;
+		// A zero existential deposit would not create the receiving accounts.
+		let _ = self.existential_deposit.set(deposit.max(1));
+		Ok(())
+	}
+
+	fn call(&self, _sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		let amount = *self.existential_deposit.get().expect("Workload is prepared before signing");
+		subxt::dynamic::tx(
+			"Balances",
+			"transfer_keep_alive",
+			vec![lookup(receiver), Value::u128(amount)],
+		)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		("Balances", "transfer_keep_alive")
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some(("Balances", "Transfer"))
+	}
+
+	fn reported_events(&self) -> Vec<(&'static str, &'static str)> {
+		vec![("Balances", "Endowed"), ("System", "NewAccount")]
+	}
+}
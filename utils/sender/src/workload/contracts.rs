@@ -0,0 +1,167 @@
+use super::{lookup, SetupPhase, Workload};
+use codec::{Decode, Encode};
+use std::sync::OnceLock;
+use subxt::{dynamic::Value, tx::DynamicPayload};
+
+const INSTANTIATE: usize = 0;
+const DISTRIBUTE: usize = 1;
+
+/// Number of transfers of the token batched in every transaction of the distribution phase.
+const DISTRIBUTION_BATCH: usize = 8;
+
+/// Selector of the `new(total_supply: Balance)` constructor of the ink! ERC20 example.
+const NEW_SELECTOR: [u8; 4] = [0x9b, 0xae, 0x9d, 0x5e];
+/// Selector of the `transfer(to: AccountId, value: Balance)` message of the ink! ERC20 example.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xa1, 0x5d, 0xa1];
+
+/// Fields of the `Contracts::Instantiated` event.
+#[derive(Decode)]
+struct Instantiated {
+	_deployer: [u8; 32],
+	contract: [u8; 32],
+}
+
+/// `transfer` calls of an ERC20 contract with the ABI of the ink! ERC20 example, deployed to
+/// `pallet-contracts` by the first sender beforehand, which then distributes the tokens to the
+/// other senders in batches.
+///
+/// A transfer which fails in the contract still succeeds as an extrinsic, so the transfers are
+/// only counted if they emit their `Transfer` event through `Contracts::ContractEmitted`.
+pub struct ContractTransfer {
+	/// Wasm code of the contract.
+	code: Vec<u8>,
+	/// Salt of the instantiation, which should be unique to the run when benchmarking the same
+	/// network several times, as the contract address depends on it.
+	salt: Vec<u8>,
+	/// Amount of the token every sender holds before the transfers, enough for any number of them.
+	pub share: u128,
+	/// Amount to send in each transaction.
+	pub amount: u128,
+	/// `(ref_time, proof_size)` gas limit of the instantiation.
+	pub instantiate_gas_limit: (u64, u64),
+	/// `(ref_time, proof_size)` gas limit of the transfers. It is charged to the block before the
+	/// transfer is executed, so it bounds the number of transfers per block and should not be much
+	/// higher than the actual weight.
+	pub call_gas_limit: (u64, u64),
+	/// Address of the contract, filled in during the first setup phase.
+	contract: OnceLock<[u8; 32]>,
+}
+
+impl ContractTransfer {
+	pub fn new(code: Vec<u8>, salt: &str) -> Self {
+		Self {
+			code,
+			salt: salt.as_bytes().to_vec(),
+			share: 1_000_000_000_000,
+			amount: 1,
+			instantiate_gas_limit: (200_000_000_000, 2_000_000),
+			call_gas_limit: (5_000_000_000, 250_000),
+			contract: OnceLock::new(),
+		}
+	}
+
+	fn transfer(&self, receiver: &[u8], amount: u128) -> DynamicPayload {
+		let contract = self.contract.get().expect("Contract is instantiated");
+		let mut data = TRANSFER_SELECTOR.to_vec();
+		data.extend_from_slice(receiver);
+		data.extend(amount.encode());
+		subxt::dynamic::tx(
+			"Contracts",
+			"call",
+			vec![
+				lookup(contract),
+				Value::u128(0),
+				weight(self.call_gas_limit),
+				Value::unnamed_variant("None", []),
+				Value::from_bytes(data),
+			],
+		)
+	}
+}
+
+fn weight((ref_time, proof_size): (u64, u64)) -> Value {
+	Value::named_composite([
+		("ref_time", Value::u128(ref_time.into())),
+		("proof_size", Value::u128(proof_size.into())),
+	])
+}
+
+impl Workload for ContractTransfer {
+	fn setup_phases(&self) -> Vec<SetupPhase> {
+		vec![
+			SetupPhase { name: "Instantiated contract", event: ("Contracts", "Instantiated") },
+			SetupPhase { name: "Distributed tokens", event: ("Utility", "BatchCompleted") },
+		]
+	}
+
+	fn setup_calls(&self, phase: usize, senders: &[Vec<u8>]) -> Vec<(usize, DynamicPayload)> {
+		if senders.is_empty() {
+			return Vec::new();
+		}
+		match phase {
+			INSTANTIATE => {
+				let data = (NEW_SELECTOR, self.share * senders.len() as u128).encode();
+				let call = subxt::dynamic::tx(
+					"Contracts",
+					"instantiate_with_code",
+					vec![
+						Value::u128(0),
+						weight(self.instantiate_gas_limit),
+						Value::unnamed_variant("None", []),
+						Value::from_bytes(&self.code),
+						Value::from_bytes(data),
+						Value::from_bytes(&self.salt),
+					],
+				);
+				vec![(0, call)]
+			},
+			// Batched, as plain `Contracts::call`s would be counted as measured transfers.
+			DISTRIBUTE => senders[1..]
+				.chunks(DISTRIBUTION_BATCH)
+				.map(|chunk| {
+					let calls = chunk
+						.iter()
+						.map(|sender| self.transfer(sender, self.share).into_value())
+						.collect::<Vec<_>>();
+					(
+						0,
+						subxt::dynamic::tx(
+							"Utility",
+							"batch_all",
+							vec![Value::unnamed_composite(calls)],
+						),
+					)
+				})
+				.collect(),
+			_ => unreachable!("ContractTransfer has two setup phases"),
+		}
+	}
+
+	fn on_setup_event(&self, phase: usize, fields: &[u8]) -> Result<(), codec::Error> {
+		if phase == INSTANTIATE {
+			let i = Instantiated::decode(&mut &fields[..])?;
+			let _ = self.contract.set(i.contract);
+		}
+		Ok(())
+	}
+
+	fn call(&self, _sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		self.transfer(receiver, self.amount)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		("Contracts", "call")
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some(("Contracts", "ContractEmitted"))
+	}
+
+	fn counts_events(&self) -> bool {
+		true
+	}
+
+	fn reports_weight(&self) -> bool {
+		true
+	}
+}
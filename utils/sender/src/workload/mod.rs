@@ -0,0 +1,111 @@
+//! Transaction workloads shared by the `stps` and `sender` binaries.
+//!
+//! A workload describes which calls the benchmark accounts submit and which of them count toward
+//! TPS. Accounts are passed around as raw account id bytes, so that the same workload can be used
+//! regardless of how the transactions are signed.
+
+use subxt::{dynamic::Value, tx::DynamicPayload, OnlineClient, PolkadotConfig};
+
+mod assets;
+mod balances;
+mod contracts;
+mod multisig;
+mod nfts;
+mod proxy;
+mod remark;
+mod xcm;
+
+pub use assets::AssetTransfer;
+pub use balances::{BalanceTransfer, FreshTransfer};
+pub use contracts::ContractTransfer;
+pub use multisig::MultisigTransfer;
+pub use nfts::NftTransfer;
+pub use proxy::ProxyTransfer;
+pub use remark::{Remark, RemarkCall};
+pub use xcm::{XcmDirection, XcmTransfer, XcmTransferType};
+
+/// A stage of a workload run before the measured transactions are sent, e.g. creating the items
+/// the senders are going to transfer. By default, every sender submits one transaction per setup
+/// phase, and the phase is complete once as many `event`s as there are setup transactions have
+/// been finalized.
+pub struct SetupPhase {
+	/// Human readable description of what the phase does, used in logs.
+	pub name: &'static str,
+	/// `(pallet, variant)` of the event emitted once per setup transaction when it has been
+	/// executed.
+	pub event: (&'static str, &'static str),
+}
+
+pub trait Workload: Send + Sync {
+	/// Reads what the workload needs from the chain before any transaction is signed, e.g. metadata
+	/// constants.
+	fn prepare(&self, _api: &OnlineClient<PolkadotConfig>) -> Result<(), subxt::Error> {
+		Ok(())
+	}
+
+	/// Setup phases to go through, in order, before the measured transactions are sent.
+	fn setup_phases(&self) -> Vec<SetupPhase> {
+		Vec::new()
+	}
+
+	/// Call submitted by `sender` in the given setup phase.
+	fn setup_call(&self, _phase: usize, _sender: &[u8]) -> DynamicPayload {
+		unreachable!("Workload has no setup phases")
+	}
+
+	/// Transactions of the given setup phase, as `(signer, call)` with `signer` the index of the
+	/// sender in `senders`. A sender may submit any number of them, with consecutive nonces.
+	fn setup_calls(&self, phase: usize, senders: &[Vec<u8>]) -> Vec<(usize, DynamicPayload)> {
+		senders
+			.iter()
+			.enumerate()
+			.map(|(i, sender)| (i, self.setup_call(phase, sender)))
+			.collect()
+	}
+
+	/// Handles the fields of a finalized event of the given setup phase, e.g. to remember the ids
+	/// of the created items.
+	fn on_setup_event(&self, _phase: usize, _fields: &[u8]) -> Result<(), codec::Error> {
+		Ok(())
+	}
+
+	/// Measured call submitted by `sender` with `receiver` as the counterparty.
+	fn call(&self, sender: &[u8], receiver: &[u8]) -> DynamicPayload;
+
+	/// `(pallet, call)` of the measured extrinsics, counted toward TPS when blocks are scraped
+	/// extrinsic by extrinsic.
+	fn counted_call(&self) -> (&'static str, &'static str);
+
+	/// `(pallet, variant)` of the event emitted once per executed unit of work, counted toward TPS
+	/// when blocks are scraped event by event. `None` if the measured extrinsics emit no event of
+	/// their own, in which case they are counted by their `counted_call` and extrinsic index.
+	fn counted_event(&self) -> Option<(&'static str, &'static str)>;
+
+	/// Whether the measured extrinsics only count toward TPS if they emit the `counted_event` on top
+	/// of being dispatched successfully, e.g. because they wrap a call which may fail on its own.
+	fn counts_events(&self) -> bool {
+		false
+	}
+
+	/// Whether the weight consumed by the measured extrinsics is worth reporting, e.g. because it
+	/// varies with the work done, unlike that of plain transfers.
+	fn reports_weight(&self) -> bool {
+		false
+	}
+
+	/// `(pallet, variant)` of side effects of the measured extrinsics worth counting per block
+	/// besides TPS, e.g. the creation of accounts.
+	fn reported_events(&self) -> Vec<(&'static str, &'static str)> {
+		Vec::new()
+	}
+}
+
+/// The given account as taken by calls expecting a lookup source: `MultiAddress::Id` for 32-byte
+/// accounts, and the plain address for 20-byte ones, as EVM-compatible chains use `IdentityLookup`.
+pub fn lookup(account: &[u8]) -> Value {
+	if account.len() == 20 {
+		Value::from_bytes(account)
+	} else {
+		Value::unnamed_variant("Id", [Value::from_bytes(account)])
+	}
+}
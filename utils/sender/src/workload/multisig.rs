@@ -0,0 +1,101 @@
+use super::{lookup, BalanceTransfer, SetupPhase, Workload};
+use codec::Encode;
+use sp_core::{hashing::blake2_256, sr25519, Pair};
+use subxt::{dynamic::Value, tx::DynamicPayload};
+
+/// `Multisig::as_multi_threshold_1` of a `Balances::transfer_keep_alive` from a multisig account
+/// of the sender and a custodian.
+///
+/// With a threshold of 1, every transfer is approved and executed by the sender alone, in a single
+/// transaction, so only the threshold-1 dispatch is measured, not the approvals of `as_multi` with
+/// higher thresholds. The multisig accounts are funded by the senders beforehand. The multisig call
+/// succeeds even if the transfer fails, so the inner `Balances::Transfer` events are counted.
+pub struct MultisigTransfer {
+	/// Transfer made on behalf of the multisig account.
+	pub transfer: BalanceTransfer,
+	/// Other signatory of every multisig account.
+	pub custodian: [u8; 32],
+}
+
+impl MultisigTransfer {
+	/// Balance every multisig account is funded with, enough for any number of transfers.
+	pub const FUNDED: u128 = 1_000_000_000_000;
+
+	/// Creates the workload with a custodian derived from `seed`. As funding a multisig account
+	/// which already exists emits no `Balances::Endowed` event, the seed should be unique to the
+	/// run when benchmarking the same network several times.
+	pub fn new(seed: &str) -> Self {
+		let custodian = sr25519::Pair::from_string(seed, None).expect("Valid seed");
+		Self { transfer: BalanceTransfer::default(), custodian: custodian.public().0 }
+	}
+
+	/// Multisig account of the sender and the custodian, as derived by `pallet-multisig` from the
+	/// sorted signatories and the threshold.
+	fn multisig(&self, sender: &[u8]) -> Vec<u8> {
+		let sender = <[u8; 32]>::try_from(sender).expect("Multisig accounts are 32-byte");
+		let mut who = vec![sender, self.custodian];
+		who.sort();
+		(b"modlpy/utilisuba", who, 1u16).using_encoded(blake2_256).to_vec()
+	}
+}
+
+impl Workload for MultisigTransfer {
+	fn setup_phases(&self) -> Vec<SetupPhase> {
+		vec![SetupPhase { name: "Funded multisig accounts", event: ("Balances", "Endowed") }]
+	}
+
+	fn setup_call(&self, _phase: usize, sender: &[u8]) -> DynamicPayload {
+		subxt::dynamic::tx(
+			"Balances",
+			// Told apart from the measured calls of a mixed-in transfer mode.
+			"transfer_allow_death",
+			vec![lookup(&self.multisig(sender)), Value::u128(Self::FUNDED)],
+		)
+	}
+
+	fn call(&self, sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		let multisig = self.multisig(sender);
+		subxt::dynamic::tx(
+			"Multisig",
+			"as_multi_threshold_1",
+			vec![
+				Value::unnamed_composite(vec![Value::from_bytes(self.custodian)]),
+				self.transfer.call(&multisig, receiver).into_value(),
+			],
+		)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		("Multisig", "as_multi_threshold_1")
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some(("Balances", "Transfer"))
+	}
+
+	fn counts_events(&self) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::hexdisplay::HexDisplay;
+
+	#[test]
+	fn multisig_of_alice_and_bob() {
+		let alice = sr25519::Pair::from_string("//Alice", None).unwrap().public().0;
+		let bob = sr25519::Pair::from_string("//Bob", None).unwrap().public().0;
+		let expected = "4c5901223c7c52585646634e70dd46ad3faf1270f84a4673b5b4a4e126474073";
+		assert_eq!(
+			HexDisplay::from(&MultisigTransfer::new("//Bob").multisig(&alice)).to_string(),
+			expected
+		);
+		// The signatories are sorted, so either of them may be the sender.
+		assert_eq!(
+			HexDisplay::from(&MultisigTransfer::new("//Alice").multisig(&bob)).to_string(),
+			expected
+		);
+	}
+}
@@ -0,0 +1,102 @@
+use super::{lookup, SetupPhase, Workload};
+use codec::Decode;
+use std::{collections::HashMap, sync::RwLock};
+use subxt::{dynamic::Value, tx::DynamicPayload};
+
+const CREATE: usize = 0;
+const MINT: usize = 1;
+
+/// Fields of the `Nfts::Created` event.
+#[derive(Decode)]
+struct Collection {
+	clid: u32,
+	owner: [u8; 32],
+}
+
+/// `Nfts::transfer` of an item minted by every sender into its own collection beforehand.
+#[derive(Default)]
+pub struct NftTransfer {
+	/// Collection created by each sender, filled in during the first setup phase.
+	collections: RwLock<HashMap<Vec<u8>, u32>>,
+}
+
+impl NftTransfer {
+	fn collection(&self, owner: &[u8]) -> Value {
+		let collections = self.collections.read().expect("Lock is not poisoned");
+		let clid = *collections.get(owner).expect("Collection exists");
+		Value::u128(clid.into())
+	}
+}
+
+impl Workload for NftTransfer {
+	fn setup_phases(&self) -> Vec<SetupPhase> {
+		vec![
+			SetupPhase { name: "Created NFT collections", event: ("Nfts", "Created") },
+			SetupPhase { name: "Minted NFTs", event: ("Nfts", "Issued") },
+		]
+	}
+
+	fn setup_call(&self, phase: usize, sender: &[u8]) -> DynamicPayload {
+		match phase {
+			CREATE => subxt::dynamic::tx(
+				"Nfts",
+				"create",
+				vec![
+					lookup(sender),
+					Value::named_composite(vec![
+						("settings", Value::primitive(0u64.into())),
+						("max_supply", Value::unnamed_variant("None", vec![])),
+						(
+							"mint_settings",
+							Value::named_composite(vec![
+								("mint_type", Value::unnamed_variant("Issuer", vec![])),
+								("price", Value::unnamed_variant("None", vec![])),
+								("start_block", Value::unnamed_variant("None", vec![])),
+								("end_block", Value::unnamed_variant("None", vec![])),
+								("default_item_settings", Value::primitive(0u64.into())),
+							]),
+						),
+					]),
+				],
+			),
+			MINT => subxt::dynamic::tx(
+				"Nfts",
+				"mint",
+				vec![
+					self.collection(sender),
+					Value::primitive(0u32.into()),
+					lookup(sender),
+					Value::unnamed_variant("None", vec![]),
+				],
+			),
+			_ => unreachable!("NftTransfer has two setup phases"),
+		}
+	}
+
+	fn on_setup_event(&self, phase: usize, fields: &[u8]) -> Result<(), codec::Error> {
+		if phase == CREATE {
+			let c = Collection::decode(&mut &fields[..])?;
+			self.collections
+				.write()
+				.expect("Lock is not poisoned")
+				.insert(c.owner.to_vec(), c.clid);
+		}
+		Ok(())
+	}
+
+	fn call(&self, sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		subxt::dynamic::tx(
+			"Nfts",
+			"transfer",
+			vec![self.collection(sender), Value::primitive(0u32.into()), lookup(receiver)],
+		)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		("Nfts", "transfer")
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some(("Nfts", "Transferred"))
+	}
+}
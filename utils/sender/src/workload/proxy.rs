@@ -0,0 +1,98 @@
+use super::{lookup, BalanceTransfer, SetupPhase, Workload};
+use codec::Decode;
+use std::{collections::HashMap, sync::RwLock};
+use subxt::{dynamic::Value, tx::DynamicPayload};
+
+const CREATE: usize = 0;
+const FUND: usize = 1;
+
+/// Leading fields of the `Proxy::PureCreated` event.
+#[derive(Decode)]
+struct PureCreated {
+	pure: [u8; 32],
+	who: [u8; 32],
+}
+
+/// `Proxy::proxy` of a `Balances::transfer_keep_alive` from a pure proxy account of the sender.
+///
+/// Every sender creates its own pure proxy account and funds it beforehand. The proxy call
+/// succeeds even if the transfer fails, so the inner `Balances::Transfer` events are counted.
+#[derive(Default)]
+pub struct ProxyTransfer {
+	/// Transfer made on behalf of the pure proxy account.
+	pub transfer: BalanceTransfer,
+	/// Pure proxy account of each sender, filled in during the first setup phase.
+	pures: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl ProxyTransfer {
+	/// Balance every pure proxy account is funded with, enough for any number of transfers.
+	pub const FUNDED: u128 = 1_000_000_000_000;
+
+	fn pure(&self, sender: &[u8]) -> Vec<u8> {
+		let pures = self.pures.read().expect("Lock is not poisoned");
+		pures.get(sender).expect("Pure proxy exists").clone()
+	}
+}
+
+impl Workload for ProxyTransfer {
+	fn setup_phases(&self) -> Vec<SetupPhase> {
+		vec![
+			SetupPhase { name: "Created pure proxies", event: ("Proxy", "PureCreated") },
+			SetupPhase { name: "Funded pure proxies", event: ("Balances", "Endowed") },
+		]
+	}
+
+	fn setup_call(&self, phase: usize, sender: &[u8]) -> DynamicPayload {
+		match phase {
+			CREATE => subxt::dynamic::tx(
+				"Proxy",
+				"create_pure",
+				vec![Value::unnamed_variant("Any", vec![]), Value::u128(0), Value::u128(0)],
+			),
+			// Not `transfer_keep_alive`, which transfer workloads mixed in would count.
+			FUND => subxt::dynamic::tx(
+				"Balances",
+				"transfer_allow_death",
+				vec![lookup(&self.pure(sender)), Value::u128(Self::FUNDED)],
+			),
+			_ => unreachable!("ProxyTransfer has two setup phases"),
+		}
+	}
+
+	fn on_setup_event(&self, phase: usize, fields: &[u8]) -> Result<(), codec::Error> {
+		if phase == CREATE {
+			let p = PureCreated::decode(&mut &fields[..])?;
+			self.pures
+				.write()
+				.expect("Lock is not poisoned")
+				.insert(p.who.to_vec(), p.pure.to_vec());
+		}
+		Ok(())
+	}
+
+	fn call(&self, sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		let pure = self.pure(sender);
+		subxt::dynamic::tx(
+			"Proxy",
+			"proxy",
+			vec![
+				lookup(&pure),
+				Value::unnamed_variant("None", vec![]),
+				self.transfer.call(&pure, receiver).into_value(),
+			],
+		)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		("Proxy", "proxy")
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some(("Balances", "Transfer"))
+	}
+
+	fn counts_events(&self) -> bool {
+		true
+	}
+}
@@ -0,0 +1,55 @@
+use super::Workload;
+use serde::{Deserialize, Serialize};
+use subxt::{dynamic::Value, tx::DynamicPayload};
+
+/// Call of the `System` pallet storing the remark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum RemarkCall {
+	/// `remark`, which does nothing but take up block space.
+	Remark,
+	/// `remark_with_event`, which also hashes the payload and emits `Remarked`.
+	RemarkWithEvent,
+}
+
+/// `System::remark` or `System::remark_with_event` with a payload of the given size. Large payloads
+/// make blocks length-bound rather than weight-bound.
+pub struct Remark {
+	/// Size of the remark payload, in bytes.
+	pub size: usize,
+	pub call: RemarkCall,
+}
+
+impl Remark {
+	pub fn new(size: usize, call: RemarkCall) -> Self {
+		Self { size, call }
+	}
+}
+
+impl Default for Remark {
+	fn default() -> Self {
+		Self::new(32, RemarkCall::RemarkWithEvent)
+	}
+}
+
+impl Workload for Remark {
+	fn call(&self, _sender: &[u8], _receiver: &[u8]) -> DynamicPayload {
+		let (pallet, call) = self.counted_call();
+		subxt::dynamic::tx(pallet, call, vec![Value::from_bytes(vec![0u8; self.size])])
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		match self.call {
+			RemarkCall::Remark => ("System", "remark"),
+			RemarkCall::RemarkWithEvent => ("System", "remark_with_event"),
+		}
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		match self.call {
+			RemarkCall::Remark => None,
+			RemarkCall::RemarkWithEvent => Some(("System", "Remarked")),
+		}
+	}
+}
@@ -0,0 +1,129 @@
+use super::Workload;
+use serde::{Deserialize, Serialize};
+use subxt::{dynamic::Value, tx::DynamicPayload};
+
+/// How the relay chain native token is moved across chains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum XcmTransferType {
+	/// `limited_reserve_transfer_assets`
+	Reserve,
+	/// `limited_teleport_assets`
+	Teleport,
+}
+
+/// Direction of the cross-chain transfers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum XcmDirection {
+	/// From relay chain senders to parachain receivers, over DMP.
+	RelayToPara,
+	/// From parachain senders to relay chain receivers, over UMP.
+	ParaToRelay,
+	/// From senders on one parachain to receivers on another one, over HRMP.
+	ParaToPara,
+}
+
+/// Transfer of an asset to the receiver on another chain, with the XCM version 4 `limited_*_assets`
+/// calls of the XCM pallet of the sending chain.
+///
+/// The relay chain native token is transferred, except for reserve transfers between parachains,
+/// which go through the relay chain when it is the reserve. The native token of the sending
+/// parachain is transferred instead, so that the messages go over HRMP.
+pub struct XcmTransfer {
+	/// Name of the XCM pallet of the sending chain.
+	pallet: &'static str,
+	call: &'static str,
+	/// `(parents, parachain)` of the destination, as seen from the sending chain.
+	dest: (u8, Option<u32>),
+	/// Parents of the location of the transferred token, as seen from the sending chain.
+	asset_parents: u8,
+	/// Amount to send in each transaction, covering the execution fees at the destination.
+	pub amount: u128,
+}
+
+impl XcmTransfer {
+	/// Transfers in the given `direction`, to the `para_id` parachain unless the destination is the
+	/// relay chain.
+	pub fn new(transfer: XcmTransferType, direction: XcmDirection, para_id: u32) -> Self {
+		let call = match transfer {
+			XcmTransferType::Reserve => "limited_reserve_transfer_assets",
+			XcmTransferType::Teleport => "limited_teleport_assets",
+		};
+		let (pallet, dest, asset_parents) = match direction {
+			XcmDirection::RelayToPara => ("XcmPallet", (0, Some(para_id)), 0),
+			XcmDirection::ParaToRelay => ("PolkadotXcm", (1, None), 1),
+			XcmDirection::ParaToPara => {
+				let asset_parents = if transfer == XcmTransferType::Reserve { 0 } else { 1 };
+				("PolkadotXcm", (1, Some(para_id)), asset_parents)
+			},
+		};
+		Self { pallet, call, dest, asset_parents, amount: 100_000_000_000 }
+	}
+}
+
+/// `VersionedLocation::V4` with the given parents and interior junctions.
+fn location(parents: u8, junctions: Vec<Value>) -> Value {
+	Value::named_composite([
+		("parents", Value::u128(parents.into())),
+		("interior", interior(junctions)),
+	])
+}
+
+fn interior(junctions: Vec<Value>) -> Value {
+	if junctions.is_empty() {
+		Value::unnamed_variant("Here", [])
+	} else {
+		let x = format!("X{}", junctions.len());
+		Value::unnamed_variant(x, [Value::unnamed_composite(junctions)])
+	}
+}
+
+impl Workload for XcmTransfer {
+	fn call(&self, _sender: &[u8], receiver: &[u8]) -> DynamicPayload {
+		let (parents, para) = self.dest;
+		let dest = location(
+			parents,
+			para.map(|id| Value::unnamed_variant("Parachain", [Value::u128(id.into())]))
+				.into_iter()
+				.collect(),
+		);
+		let beneficiary = location(
+			0,
+			vec![Value::named_variant(
+				"AccountId32",
+				[
+					("network", Value::unnamed_variant("None", [])),
+					("id", Value::from_bytes(receiver)),
+				],
+			)],
+		);
+		let asset = Value::named_composite([
+			("id", Value::unnamed_composite([location(self.asset_parents, Vec::new())])),
+			("fun", Value::unnamed_variant("Fungible", [Value::u128(self.amount)])),
+		]);
+		let assets = Value::unnamed_composite([Value::unnamed_composite([asset])]);
+
+		subxt::dynamic::tx(
+			self.pallet,
+			self.call,
+			vec![
+				Value::unnamed_variant("V4", [dest]),
+				Value::unnamed_variant("V4", [beneficiary]),
+				Value::unnamed_variant("V4", [assets]),
+				Value::u128(0),
+				Value::unnamed_variant("Unlimited", []),
+			],
+		)
+	}
+
+	fn counted_call(&self) -> (&'static str, &'static str) {
+		(self.pallet, self.call)
+	}
+
+	fn counted_event(&self) -> Option<(&'static str, &'static str)> {
+		Some((self.pallet, "Sent"))
+	}
+}